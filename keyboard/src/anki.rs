@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Write;
+
+use crate::kana_converter::RomanjiToKanaConverter;
+use crate::lessons::{Book, Phrase};
+use crate::progress::Progress;
+
+pub const DEFAULT_FRONT: &str = "{en}";
+pub const DEFAULT_BACK: &str = "{kanji}<br>{jp}<br>{romaji}";
+
+// how every card of the deck looks, the templates use {en}, {jp},
+// {kanji}, {romaji}, {lesson} and {section}
+#[derive(Debug, Clone)]
+pub struct Deck {
+    pub name: String,
+    pub front: String,
+    pub back: String,
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Deck {
+            name: String::from("Genki"),
+            front: String::from(DEFAULT_FRONT),
+            back: String::from(DEFAULT_BACK),
+        }
+    }
+}
+
+// writes a tab separated file anki imports as basic notes with the tags
+// in the third column, the header lines tell anki how to read it. with
+// progress each back ends with how the phrase went so far
+pub fn export(
+    book: &Book,
+    sections: &[(usize, usize)],
+    deck: &Deck,
+    kana_converter: &RomanjiToKanaConverter,
+    progress: Option<&Progress>,
+    mut writer: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(writer, "#separator:tab")?;
+    writeln!(writer, "#html:true")?;
+    writeln!(writer, "#notetype:Basic")?;
+    writeln!(writer, "#deck:{}", deck.name)?;
+    writeln!(writer, "#tags column:3")?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .from_writer(writer);
+    for &(lesson_idx, section_idx) in sections {
+        let lesson = &book[lesson_idx];
+        let section = &lesson.sections[section_idx];
+        for phrase in &section.phrases {
+            let fields = [
+                ("en", escape_html(&phrase.en)),
+                ("jp", escape_html(&phrase.jp)),
+                ("kanji", escape_html(phrase.kanji.as_deref().unwrap_or(""))),
+                (
+                    "romaji",
+                    escape_html(&kana_converter.to_romanji(&phrase.jp)),
+                ),
+                ("lesson", lesson.index.to_string()),
+                ("section", escape_html(&section.name)),
+            ];
+            let mut back = render(&deck.back, &fields);
+            if let Some(progress) = progress {
+                back.push_str(&format!(
+                    "<br><small>{}</small>",
+                    history(progress, &phrase.id)
+                ));
+            }
+            writer.write_record([
+                render(&deck.front, &fields),
+                back,
+                tags(lesson.index, &section.name, phrase).join(" "),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// fills in the {field} placeholders, lines left empty because a phrase
+// has no kanji are dropped
+fn render(template: &str, fields: &[(&str, String)]) -> String {
+    template
+        .split("<br>")
+        .map(|line| {
+            fields
+                .iter()
+                .fold(String::from(line), |line, (name, value)| {
+                    line.replace(&format!("{{{name}}}"), value)
+                })
+        })
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<String>>()
+        .join("<br>")
+}
+
+// anki tags cant contain spaces, "Greetings (あいさつ)" becomes "greetings"
+fn tags(lesson_index: usize, section_name: &str, phrase: &Phrase) -> Vec<String> {
+    let mut tags = vec![
+        format!("lesson-{lesson_index}"),
+        format!("lesson-{lesson_index}::{}", tag(section_name)),
+    ];
+    tags.extend(phrase.tags.iter().map(|name| tag(name)));
+    // first one of each tag stays, wherever the repeats are
+    let mut seen = HashSet::<String>::new();
+    tags.retain(|tag| !tag.is_empty() && !tag.ends_with("::") && seen.insert(tag.clone()));
+    tags
+}
+
+fn tag(name: &str) -> String {
+    let name = name
+        .split_once(" (")
+        .map(|(short_name, _)| short_name)
+        .unwrap_or(name);
+    name.to_lowercase()
+        .split(|value: char| !value.is_alphanumeric() && value != '-')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+fn history(progress: &Progress, key: &str) -> String {
+    let phrase_progress = progress.get(key);
+    let mut history = if phrase_progress.seen == 0 {
+        String::from("not practiced yet")
+    } else {
+        format!(
+            "{} of {} correct, {} in a row",
+            phrase_progress.correct, phrase_progress.seen, phrase_progress.streak
+        )
+    };
+    if let Some(mark) = progress.mark(key) {
+        history.push_str(&format!(", marked {}", mark.label()));
+    }
+    history
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Mark;
+    use crate::resources::Resources;
+
+    #[test]
+    fn test_render() {
+        let fields = [
+            ("en", String::from("to wait")),
+            ("jp", String::from("まつ")),
+            ("kanji", String::new()),
+        ];
+        let test_cases = vec![
+            ("{en}", "to wait"),
+            ("{kanji}<br>{jp}", "まつ"),
+            ("{jp} ({kanji})", "まつ ()"),
+            ("<b>{en}</b> {unknown}", "<b>to wait</b> {unknown}"),
+        ];
+        for (template, expected) in test_cases {
+            assert_eq!(
+                render(template, &fields),
+                expected,
+                "Failed for template: {}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_tags() {
+        let phrase = Phrase {
+            en: String::from("to wait"),
+            jp: String::from("まつ"),
+            tags: vec![String::from("u-verb"), String::from("Time & Days")],
+            ..Phrase::default()
+        };
+        let test_cases = vec![
+            (
+                "Greetings (あいさつ)",
+                vec!["lesson-0", "lesson-0::greetings", "u-verb", "time-days"],
+            ),
+            (
+                "U-verbs",
+                vec!["lesson-0", "lesson-0::u-verbs", "u-verb", "time-days"],
+            ),
+            ("", vec!["lesson-0", "u-verb", "time-days"]),
+        ];
+        for (section_name, expected) in test_cases {
+            assert_eq!(
+                tags(0, section_name, &phrase),
+                expected,
+                "Failed for section: {}",
+                section_name
+            );
+        }
+
+        // repeats are dropped even when they aren't next to each other
+        let phrase = Phrase {
+            tags: vec![
+                String::from("U-verb"),
+                String::from("time & days"),
+                String::from("u verb"),
+                String::from("lesson-0"),
+            ],
+            ..phrase
+        };
+        assert_eq!(
+            tags(0, "U-verbs", &phrase),
+            vec!["lesson-0", "lesson-0::u-verbs", "u-verb", "time-days"]
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let resources = Resources::default();
+        let kana_converter = RomanjiToKanaConverter::from_file(&resources.kana_path()).unwrap();
+        let book = Book::from_json(
+            r#"{"lessons": [{"index": 3, "name_en": "Weekend", "name_jp": "しゅうまつ", "sections": [
+                {"name": "U-verbs", "phrases": [
+                    {"en": "to wait (~を)", "jp": "まつ", "kanji": "待つ"},
+                    {"en": "<tab>\there", "jp": "ここ"}
+                ]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let mut progress = Progress::default();
+        progress.record(Progress::key(3, 0, 0), true);
+        progress.record(Progress::key(3, 0, 0), false);
+        progress.toggle_mark(Progress::key(3, 0, 0), Mark::Hard);
+
+        let mut output = Vec::<u8>::new();
+        export(
+            &book,
+            &[(0, 0)],
+            &Deck::default(),
+            &kana_converter,
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "#separator:tab\n#html:true\n#notetype:Basic\n#deck:Genki\n#tags column:3\n\
+             to wait (~を)\t待つ<br>まつ<br>matsu\tlesson-3 lesson-3::u-verbs\n\
+             \"&lt;tab&gt;\there\"\tここ<br>koko\tlesson-3 lesson-3::u-verbs\n"
+        );
+
+        let deck = Deck {
+            name: String::from("Class"),
+            front: String::from("{jp}"),
+            back: String::from("{en}"),
+        };
+        let mut output = Vec::<u8>::new();
+        export(
+            &book,
+            &[(0, 0)],
+            &deck,
+            &kana_converter,
+            Some(&progress),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[3], "#deck:Class");
+        assert_eq!(
+            lines[5],
+            "まつ\tto wait (~を)<br><small>1 of 2 correct, 0 in a row, marked hard</small>\tlesson-3 lesson-3::u-verbs"
+        );
+        assert!(lines[6].contains("<small>not practiced yet</small>"));
+    }
+}
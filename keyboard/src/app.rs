@@ -1,206 +1,1899 @@
-use crate::kana_converter::RomanjiToKanaConverter;
-use crate::kanji_converter::HiragaToKanjiConverter;
-use crate::lessons::Book;
+use crate::composer::Composer;
+use crate::furigana::Furigana;
+use crate::grading;
+use crate::kana_converter::to_hiragana;
+use crate::kana_drill::{self, KanaPool};
+use crate::keymap::Keymap;
+use crate::lessons::{Book, Lesson, Phrase, Section};
+use crate::library::Library;
+use crate::preferences::Preferences;
+use crate::progress::{Mark, Progress};
+use crate::resources::Resources;
+use crate::theme::{Colors, Theme, ThemeName};
 
-use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::str::FromStr;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CurrentScreen {
     Welcome,
+    BookSelect,
     LessonSelect,
     Review,
+    Browse,
+    Search,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CurrentSelection {
     Lesson,
     Section,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// input handled by App::handle_event, independent of the terminal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Quit,
+    // leave the current screen, quits from the welcome and lesson select screens
+    Back,
+    Continue,
+    StartSession,
+    MoveCursor(Move),
+    ToggleSelection,
+    ClearSelection,
+    CycleDirectionMode,
+    AdjustDirectionWeight(i16),
+    CycleTheme,
+    // open the vocabulary of the highlighted lesson or section
+    BrowseVocabulary,
+    // search the vocabulary of every lesson
+    SearchVocabulary,
+    Submit,
+    // Left shrinks and Right grows the highlighted kana span
+    ExtendSelection(Move),
+    ConvertSpan,
+    Input(char),
+    DeleteChar,
+    DeleteWord,
+    ClearInput,
+    // mark the asked phrase, the same key again removes the mark
+    MarkKnown,
+    MarkHard,
+    // the typed answer becomes the note of the flag
+    FlagBadData,
+    // readings above the kanji of a question
+    ToggleFurigana,
+    CycleDrill,
+    // the kana asked by the kana drill, switches to the kana drill first
+    CycleKanaPools,
+    ToggleHelp,
+}
+
+impl Action {
+    // actions that may repeat while their key is held down
+    pub fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            Action::MoveCursor(_)
+                | Action::ExtendSelection(_)
+                | Action::AdjustDirectionWeight(_)
+                | Action::Input(_)
+                | Action::DeleteChar
+                | Action::DeleteWord
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Transition {
+    Stay,
+    Screen(CurrentScreen),
+    Quit,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TranslationDirection {
     ToEN,
     ToJP,
 }
 
+impl TranslationDirection {
+    fn flip(self) -> Self {
+        match self {
+            TranslationDirection::ToEN => TranslationDirection::ToJP,
+            TranslationDirection::ToJP => TranslationDirection::ToEN,
+        }
+    }
+}
+
+// how the translation direction of each question is chosen
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DirectionMode {
+    ToJP,
+    ToEN,
+    Alternate,
+    Random { to_jp_percent: u8 },
+}
+
+impl Default for DirectionMode {
+    fn default() -> Self {
+        DirectionMode::Random { to_jp_percent: 50 }
+    }
+}
+
+impl DirectionMode {
+    pub fn next(self) -> Self {
+        match self {
+            DirectionMode::Random { .. } => DirectionMode::ToJP,
+            DirectionMode::ToJP => DirectionMode::ToEN,
+            DirectionMode::ToEN => DirectionMode::Alternate,
+            DirectionMode::Alternate => DirectionMode::default(),
+        }
+    }
+
+    // change the weight of a random direction, other modes are unaffected
+    pub fn adjust_weight(self, delta: i16) -> Self {
+        match self {
+            DirectionMode::Random { to_jp_percent } => DirectionMode::Random {
+                to_jp_percent: (to_jp_percent as i16 + delta).clamp(0, 100) as u8,
+            },
+            mode => mode,
+        }
+    }
+
+    pub fn pick<R: Rng>(
+        self,
+        prev_direction: Option<TranslationDirection>,
+        rng: &mut R,
+    ) -> TranslationDirection {
+        match self {
+            DirectionMode::ToJP => TranslationDirection::ToJP,
+            DirectionMode::ToEN => TranslationDirection::ToEN,
+            // recognition comes first when alternating
+            DirectionMode::Alternate => prev_direction
+                .map(TranslationDirection::flip)
+                .unwrap_or(TranslationDirection::ToEN),
+            DirectionMode::Random { to_jp_percent } => {
+                if rng.gen_range(0..100) < to_jp_percent {
+                    TranslationDirection::ToJP
+                } else {
+                    TranslationDirection::ToEN
+                }
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DirectionMode::ToJP => String::from("EN → JP"),
+            DirectionMode::ToEN => String::from("JP → EN"),
+            DirectionMode::Alternate => String::from("alternating"),
+            DirectionMode::Random { to_jp_percent } => {
+                format!("random ({}% EN → JP)", to_jp_percent)
+            }
+        }
+    }
+}
+
+// parses to-jp, to-en, alternate, random or random=<percent EN → JP>
+impl FromStr for DirectionMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "to-jp" => Ok(DirectionMode::ToJP),
+            "to-en" => Ok(DirectionMode::ToEN),
+            "alternate" => Ok(DirectionMode::Alternate),
+            "random" => Ok(DirectionMode::default()),
+            _ => {
+                let to_jp_percent = value
+                    .strip_prefix("random=")
+                    .and_then(|percent| percent.parse::<u8>().ok())
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid direction '{value}', expected to-jp, to-en, alternate, random or random=<0-100>"
+                        )
+                    })?;
+                Ok(DirectionMode::Random { to_jp_percent })
+            }
+        }
+    }
+}
+
+// what the questions of a session ask for
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Drill {
+    // english to japanese or back, as the direction mode says
+    #[default]
+    Translate,
+    // the kanji of a phrase, answered with its reading in romaji
+    Reading,
+    // every kanji of the phrases on its own, any of its readings counts
+    KanjiReading,
+    // the kana and english of a phrase, answered with its kanji picked
+    // from the candidates
+    Writing,
+    // single kana from the kana rules, read in romaji or typed from it as
+    // the direction mode says
+    Kana,
+}
+
+impl Drill {
+    pub fn next(self) -> Self {
+        match self {
+            Drill::Translate => Drill::Reading,
+            Drill::Reading => Drill::KanjiReading,
+            Drill::KanjiReading => Drill::Writing,
+            Drill::Writing => Drill::Kana,
+            Drill::Kana => Drill::Translate,
+        }
+    }
+
+    // a kanji or kana is the same in every book, so their progress isn't
+    // kept per book
+    pub fn shares_progress(self) -> bool {
+        matches!(self, Drill::KanjiReading | Drill::Kana)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Drill::Translate => "translate",
+            Drill::Reading => "read kanji words",
+            Drill::KanjiReading => "read single kanji",
+            Drill::Writing => "write kanji",
+            Drill::Kana => "kana",
+        }
+    }
+}
+
+impl FromStr for Drill {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "translate" => Ok(Drill::Translate),
+            "reading" => Ok(Drill::Reading),
+            "kanji-reading" => Ok(Drill::KanjiReading),
+            "writing" => Ok(Drill::Writing),
+            "kana" => Ok(Drill::Kana),
+            _ => Err(format!(
+                "invalid drill '{value}', expected translate, reading, kanji-reading, writing or kana"
+            )),
+        }
+    }
+}
+
 pub struct Context {
     pub current_screen: CurrentScreen,
     pub current_selection: CurrentSelection,
 
+    // highlighted book on the book picker
+    pub shelf_idx: usize,
+
     pub lesson_idx: usize,
     pub section_idx: Option<usize>,
+    pub selected_lessons: BTreeSet<usize>,
+    pub selected_sections: BTreeSet<(usize, usize)>,
+
+    // (lesson index, section index) of every section in the session
+    pub pool: Vec<(usize, usize)>,
+    pub pool_idx: usize,
     pub phrase_idx: usize,
     pub translation_direction: TranslationDirection,
+    pub direction_mode: DirectionMode,
+    pub drill: Drill,
+    // the questions of drills other than translate, the pool points into
+    // it instead of the book
+    pub drill_book: Option<Book>,
+    // what the kana drill asks
+    pub kana_pools: Vec<KanaPool>,
 
-    pub prev_section_idx: Option<usize>,
+    pub prev_pool_idx: Option<usize>,
     pub prev_phrase_idx: Option<usize>,
     pub prev_translation_direction: Option<TranslationDirection>,
     pub prev_answer: Option<String>,
+    pub prev_correct: Option<bool>,
+
+    // questions per session, unlimited when None
+    pub session_len: Option<usize>,
+    pub answered: usize,
 
-    pub kanji_offset: usize,
+    // every session replays the same question order for a seed
+    pub seed: u64,
+    pub rng: StdRng,
 
-    pub randomize_section: bool,
-    pub asked_questions: Vec<HashSet<usize>>,
+    // ids of the phrases asked this round
+    pub asked_questions: HashSet<String>,
+    // ids of hard phrases asked once this round
+    pub hard_asked: HashSet<String>,
+
+    // search typed on the browse screen and the highlighted row
+    pub browse_query: String,
+    pub browse_idx: usize,
+
+    // search over the whole book and the highlighted hit
+    pub search_query: String,
+    pub search_idx: usize,
 }
 
 impl Context {
-    fn new() -> Self {
+    fn new(
+        direction_mode: DirectionMode,
+        drill: Drill,
+        kana_pools: Vec<KanaPool>,
+        seed: u64,
+    ) -> Self {
         Context {
             current_screen: CurrentScreen::Welcome,
             current_selection: CurrentSelection::Lesson,
+            shelf_idx: 0,
             lesson_idx: 0,
             section_idx: None,
+            selected_lessons: BTreeSet::<usize>::new(),
+            selected_sections: BTreeSet::<(usize, usize)>::new(),
+            pool: Vec::<(usize, usize)>::new(),
+            pool_idx: 0,
             phrase_idx: 0,
             translation_direction: TranslationDirection::ToEN,
-            prev_section_idx: None,
+            direction_mode,
+            drill,
+            drill_book: None,
+            kana_pools,
+            prev_pool_idx: None,
             prev_phrase_idx: None,
             prev_translation_direction: None,
             prev_answer: None,
-            kanji_offset: 0,
-            randomize_section: false,
-            asked_questions: Vec::<HashSet<usize>>::new(),
+            prev_correct: None,
+            session_len: None,
+            answered: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            asked_questions: HashSet::<String>::new(),
+            hard_asked: HashSet::<String>::new(),
+            browse_query: String::new(),
+            browse_idx: 0,
+            search_query: String::new(),
+            search_idx: 0,
         }
     }
+
+    pub fn has_selection(&self) -> bool {
+        !self.selected_lessons.is_empty() || !self.selected_sections.is_empty()
+    }
+
+    pub fn session_finished(&self) -> bool {
+        self.session_len
+            .is_some_and(|session_len| self.answered >= session_len)
+    }
 }
 
 pub struct App {
+    // the book of the open shelf
     pub book: Book,
-    kana_converter: RomanjiToKanaConverter,
-    kanji_converter: HiragaToKanjiConverter,
+    pub library: Library,
+    pub open_shelf_idx: usize,
+    pub composer: Composer,
+    pub furigana: Furigana,
     pub context: Context,
-
-    pub romanji: String,
-    pub kana: String,
-    pub kanji: String,
-
-    pub highlighted_kanji: Vec<char>,
-    pub kana_offset: usize,
-    pub kana_len: usize,
-    kanji_offsets: Vec<(usize, usize, usize)>,
+    pub preferences: Preferences,
+    pub progress: Progress,
+    pub keymap: Keymap,
+    pub show_help: bool,
+    pub theme: Theme,
+    // config colours kept across theme changes
+    pub colors: Colors,
 }
 
 impl App {
-    pub fn new() -> Self {
-        App {
-            book: Book::new(),
-            kana_converter: RomanjiToKanaConverter::new(),
-            kanji_converter: HiragaToKanjiConverter::new(),
-            context: Context::new(),
-            romanji: String::new(),
-            kana: String::new(),
-            kanji: String::new(),
-            highlighted_kanji: Vec::new(),
-            kana_offset: 0,
-            kana_len: 1,
-            kanji_offsets: Vec::new(),
-        }
-    }
-
-    pub fn push_char(&mut self, value: char) {
-        self.romanji.push(value);
-        self.kana = self.kana_converter.convert(&self.romanji);
-    }
-
-    pub fn pop_char(&mut self) {
-        self.romanji.pop();
-        self.kana = self.kana_converter.convert(&self.romanji);
-    }
-
-    pub fn push_kanji_offset(&mut self, offset: (usize, usize, usize)) {
-        let start = offset.0;
-        let end = offset.0 + offset.1;
-        let kanji_list_offset = offset.2;
-        assert!(start < self.kana.chars().count() && end <= self.kana.chars().count());
-        let kana_substr: String = self.kana.chars().take(end).skip(start).collect();
-        let kanji_list = self.kanji_converter.convert(&kana_substr);
-        if kanji_list_offset < kanji_list.len() {
-            // if exact match, undo matching
-            if let Some(index) = self
-                .kanji_offsets
-                .iter()
-                .position(|&kanji_offset| kanji_offset == offset)
+    pub fn new(
+        resources: &Resources,
+        preferences: Preferences,
+        seed: Option<u64>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().r#gen());
+        let library = Library::load(resources)?;
+        let composer = Composer::new(resources)?;
+        Ok(App {
+            book: library.shelves[0].book.clone(),
+            library,
+            open_shelf_idx: 0,
+            furigana: Furigana::new(composer.kanji_converter()),
+            composer,
+            context: Context::new(
+                preferences.direction_mode,
+                preferences.drill,
+                preferences.kana_pools.clone(),
+                seed,
+            ),
+            theme: Theme::new(preferences.theme, Colors::default()),
+            preferences,
+            progress: Progress::default(),
+            keymap: Keymap::default(),
+            show_help: false,
+            colors: Colors::default(),
+        })
+    }
+
+    pub fn set_direction_mode(&mut self, direction_mode: DirectionMode) {
+        self.context.direction_mode = direction_mode;
+        self.preferences.direction_mode = direction_mode;
+    }
+
+    pub fn set_drill(&mut self, drill: Drill) {
+        self.context.drill = drill;
+        self.preferences.drill = drill;
+    }
+
+    pub fn set_kana_pools(&mut self, kana_pools: Vec<KanaPool>) {
+        self.context.kana_pools = kana_pools.clone();
+        self.preferences.kana_pools = kana_pools;
+    }
+
+    pub fn set_theme(&mut self, theme_name: ThemeName) {
+        self.theme = Theme::new(theme_name, self.colors);
+        self.preferences.theme = theme_name;
+    }
+
+    pub fn toggle_furigana(&mut self) {
+        self.preferences.hide_furigana = !self.preferences.hide_furigana;
+    }
+
+    pub fn handle_event(&mut self, action: Action) -> Transition {
+        if action == Action::Quit {
+            return Transition::Quit;
+        }
+        // the help overlay takes all input until it is closed
+        if action == Action::ToggleHelp {
+            self.show_help = !self.show_help;
+            return Transition::Stay;
+        }
+        if self.show_help {
+            if action == Action::Back {
+                self.show_help = false;
+            }
+            return Transition::Stay;
+        }
+        let screen = self.context.current_screen;
+        let transition = match screen {
+            CurrentScreen::Welcome => self.handle_welcome(action),
+            CurrentScreen::BookSelect => self.handle_book_select(action),
+            CurrentScreen::LessonSelect => self.handle_lesson_select(action),
+            CurrentScreen::Review => self.handle_review(action),
+            CurrentScreen::Browse => self.handle_browse(action),
+            CurrentScreen::Search => self.handle_search(action),
+        };
+        match transition {
+            Transition::Stay if self.context.current_screen != screen => {
+                Transition::Screen(self.context.current_screen)
+            }
+            transition => transition,
+        }
+    }
+
+    fn handle_welcome(&mut self, action: Action) -> Transition {
+        match action {
+            Action::Back => Transition::Quit,
+            _ => {
+                self.context.current_screen = self.home_screen();
+                Transition::Stay
+            }
+        }
+    }
+
+    // the book picker is only worth showing with more than one book
+    fn home_screen(&self) -> CurrentScreen {
+        if self.library.shelves.len() > 1 {
+            CurrentScreen::BookSelect
+        } else {
+            CurrentScreen::LessonSelect
+        }
+    }
+
+    fn handle_book_select(&mut self, action: Action) -> Transition {
+        match action {
+            Action::Back => return Transition::Quit,
+            Action::MoveCursor(Move::Up) => {
+                self.context.shelf_idx = self.context.shelf_idx.saturating_sub(1);
+            }
+            Action::MoveCursor(Move::Down)
+                if self.context.shelf_idx + 1 < self.library.shelves.len() =>
             {
-                self.kanji_offsets.remove(index);
-            } else {
-                // remove colliding offsets
-                self.kanji_offsets.retain(|&kanji_offset| {
-                    let start = offset.0;
-                    let end = offset.0 + offset.1;
-                    return !(start >= kanji_offset.0 && start < kanji_offset.0 + kanji_offset.1)
-                        && !(end > kanji_offset.0 && end <= kanji_offset.0 + kanji_offset.1);
-                });
-                self.kanji_offsets.push(offset);
+                self.context.shelf_idx += 1;
+            }
+            Action::Continue => {
+                self.open_shelf(self.context.shelf_idx);
+                self.context.current_screen = CurrentScreen::LessonSelect;
+            }
+            Action::CycleTheme => self.set_theme(self.theme.name.next()),
+            _ => {}
+        }
+        Transition::Stay
+    }
+
+    // switching books starts the lesson list over, the old selection
+    // points at lessons of the other book
+    pub fn open_shelf(&mut self, shelf_idx: usize) {
+        assert!(shelf_idx < self.library.shelves.len());
+        self.context.shelf_idx = shelf_idx;
+        if shelf_idx == self.open_shelf_idx {
+            return;
+        }
+        self.open_shelf_idx = shelf_idx;
+        self.book = self.library.shelves[shelf_idx].book.clone();
+        self.context.current_selection = CurrentSelection::Lesson;
+        self.context.lesson_idx = 0;
+        self.context.section_idx = None;
+        self.clear_selection();
+    }
+
+    fn handle_lesson_select(&mut self, action: Action) -> Transition {
+        match action {
+            Action::Back if self.home_screen() == CurrentScreen::LessonSelect => {
+                return Transition::Quit;
+            }
+            Action::Back => self.context.current_screen = CurrentScreen::BookSelect,
+            Action::StartSession => {
+                self.start_session();
+            }
+            Action::ToggleSelection => match self.context.current_selection {
+                CurrentSelection::Lesson => self.toggle_lesson(),
+                CurrentSelection::Section => self.toggle_section(),
+            },
+            Action::ClearSelection => self.clear_selection(),
+            Action::CycleDirectionMode => {
+                self.set_direction_mode(self.context.direction_mode.next());
+            }
+            Action::AdjustDirectionWeight(delta) => {
+                self.set_direction_mode(self.context.direction_mode.adjust_weight(delta));
+            }
+            Action::CycleTheme => self.set_theme(self.theme.name.next()),
+            Action::ToggleFurigana => self.toggle_furigana(),
+            Action::CycleDrill => self.set_drill(self.context.drill.next()),
+            Action::CycleKanaPools if self.context.drill != Drill::Kana => {
+                self.set_drill(Drill::Kana);
+            }
+            Action::CycleKanaPools => {
+                self.set_kana_pools(kana_drill::next_pools(&self.context.kana_pools));
+            }
+            Action::BrowseVocabulary => {
+                self.context.browse_query.clear();
+                self.context.browse_idx = 0;
+                self.context.current_screen = CurrentScreen::Browse;
+            }
+            Action::SearchVocabulary => {
+                self.context.search_query.clear();
+                self.context.search_idx = 0;
+                self.context.current_screen = CurrentScreen::Search;
+            }
+            Action::MoveCursor(direction) => self.move_lesson_cursor(direction),
+            _ => {}
+        }
+        Transition::Stay
+    }
+
+    fn handle_browse(&mut self, action: Action) -> Transition {
+        match action {
+            Action::Back => self.context.current_screen = CurrentScreen::LessonSelect,
+            Action::MoveCursor(Move::Up) => {
+                self.context.browse_idx = self.context.browse_idx.saturating_sub(1);
+            }
+            Action::MoveCursor(Move::Down) => {
+                let row_count = self.browse_rows().len();
+                if self.context.browse_idx + 1 < row_count {
+                    self.context.browse_idx += 1;
+                }
+            }
+            Action::Input(value) => {
+                self.context.browse_query.push(value);
+                self.context.browse_idx = 0;
+            }
+            Action::DeleteChar => {
+                self.context.browse_query.pop();
+                self.context.browse_idx = 0;
+            }
+            Action::ClearInput => {
+                self.context.browse_query.clear();
+                self.context.browse_idx = 0;
+            }
+            _ => {}
+        }
+        Transition::Stay
+    }
+
+    fn handle_search(&mut self, action: Action) -> Transition {
+        match action {
+            Action::Back => self.context.current_screen = CurrentScreen::LessonSelect,
+            // drill the section of the highlighted hit
+            Action::StartSession => {
+                if let Some(&(lesson_idx, section_idx, _)) =
+                    self.search_rows().get(self.context.search_idx)
+                {
+                    self.start_pool(vec![(lesson_idx, section_idx)]);
+                }
+            }
+            Action::MoveCursor(Move::Up) => {
+                self.context.search_idx = self.context.search_idx.saturating_sub(1);
+            }
+            Action::MoveCursor(Move::Down) => {
+                let row_count = self.search_rows().len();
+                if self.context.search_idx + 1 < row_count {
+                    self.context.search_idx += 1;
+                }
+            }
+            Action::Input(value) => {
+                self.context.search_query.push(value);
+                self.context.search_idx = 0;
+            }
+            Action::DeleteChar => {
+                self.context.search_query.pop();
+                self.context.search_idx = 0;
+            }
+            Action::ClearInput => {
+                self.context.search_query.clear();
+                self.context.search_idx = 0;
+            }
+            _ => {}
+        }
+        Transition::Stay
+    }
+
+    // (lesson index, section index, phrase index) of every phrase in the
+    // book matching the search, nothing until something is typed
+    pub fn search_rows(&self) -> Vec<(usize, usize, usize)> {
+        let mut rows = Vec::<(usize, usize, usize)>::new();
+        if self.context.search_query.trim().is_empty() {
+            return rows;
+        }
+        for (lesson_idx, lesson) in self.book.lessons.iter().enumerate() {
+            for (section_idx, section) in lesson.sections.iter().enumerate() {
+                for (phrase_idx, phrase) in section.phrases.iter().enumerate() {
+                    if self.phrase_matches(phrase, &self.context.search_query) {
+                        rows.push((lesson_idx, section_idx, phrase_idx));
+                    }
+                }
+            }
+        }
+        rows
+    }
+
+    // (lesson index, section index, phrase index) of the phrases in the
+    // highlighted lesson or section that match the search
+    pub fn browse_rows(&self) -> Vec<(usize, usize, usize)> {
+        let lesson_idx = self.context.lesson_idx;
+        assert!(lesson_idx < self.book.lessons.len());
+        let lesson = &self.book.lessons[lesson_idx];
+        let mut rows = Vec::<(usize, usize, usize)>::new();
+        for (section_idx, section) in lesson.sections.iter().enumerate() {
+            if self
+                .context
+                .section_idx
+                .is_some_and(|selected_idx| selected_idx != section_idx)
+            {
+                continue;
+            }
+            for (phrase_idx, phrase) in section.phrases.iter().enumerate() {
+                if self.phrase_matches(phrase, &self.context.browse_query) {
+                    rows.push((lesson_idx, section_idx, phrase_idx));
+                }
+            }
+        }
+        rows
+    }
+
+    // english and romanji match case insensitively, kana matches either
+    // script and romanji is also tried converted to kana
+    pub fn phrase_matches(&self, phrase: &Phrase, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+        let converter = self.composer.kana_converter();
+        let jp = to_hiragana(&phrase.jp);
+        phrase.en.to_lowercase().contains(&query)
+            || converter.to_romanji(&phrase.jp).contains(&query)
+            || jp.contains(&to_hiragana(&query))
+            || jp.contains(&converter.convert(&query))
+            || phrase
+                .kanji
+                .as_ref()
+                .is_some_and(|kanji| kanji.contains(&query))
+    }
+
+    // progress store key of a phrase of the open book
+    pub fn progress_key(&self, phrase: &Phrase) -> String {
+        self.library.shelves[self.open_shelf_idx].progress_key(phrase)
+    }
+
+    // progress store key of a question of the session
+    pub fn session_key(&self, phrase: &Phrase) -> String {
+        if self.context.drill_book.is_some() && self.context.drill.shares_progress() {
+            return phrase.id.clone();
+        }
+        self.progress_key(phrase)
+    }
+
+    // progress of edited phrases follows them to their new id
+    pub fn migrate_progress(&mut self) {
+        for shelf in &self.library.shelves {
+            shelf.migrate_progress(&mut self.progress);
+        }
+    }
+
+    fn move_lesson_cursor(&mut self, direction: Move) {
+        assert!(self.context.lesson_idx < self.book.lessons.len());
+        let lesson_count = self.book.lessons.len();
+        let section_count = self.book[self.context.lesson_idx].sections.len();
+        match (self.context.current_selection, direction) {
+            (CurrentSelection::Lesson, Move::Down) => {
+                self.context.lesson_idx = (self.context.lesson_idx + 1) % lesson_count;
+            }
+            (CurrentSelection::Lesson, Move::Up) => {
+                self.context.lesson_idx =
+                    (self.context.lesson_idx + lesson_count - 1) % lesson_count;
+            }
+            (CurrentSelection::Lesson, Move::Right) => {
+                self.context.current_selection = CurrentSelection::Section;
+                self.context.section_idx = Some(0);
+            }
+            (CurrentSelection::Section, Move::Down) => {
+                let section_idx = self.context.section_idx.expect("section index not set");
+                self.context.section_idx = Some((section_idx + 1) % section_count);
+            }
+            (CurrentSelection::Section, Move::Up) => {
+                let section_idx = self.context.section_idx.expect("section index not set");
+                self.context.section_idx = Some((section_idx + section_count - 1) % section_count);
+            }
+            (CurrentSelection::Section, Move::Left) => {
+                self.context.current_selection = CurrentSelection::Lesson;
+                self.context.section_idx = None;
             }
-            self.highlighted_kanji = kanji_list;
+            _ => {}
         }
     }
 
-    pub fn update_kanji(&mut self) {
-        self.kanji = self.kana.clone();
-        // remove offsets that no longer exist because of a backspace
-        self.kanji_offsets.retain(|kanji_offset| {
-            let start = kanji_offset.0;
-            let end = kanji_offset.0 + kanji_offset.1;
-            return start <= self.kana.chars().count() && end <= self.kana.chars().count();
+    fn handle_review(&mut self, action: Action) -> Transition {
+        if self.context.session_finished() {
+            if let Action::Back | Action::Submit = action {
+                self.end_session();
+            }
+            return Transition::Stay;
+        }
+        match action {
+            Action::Back => self.end_session(),
+            Action::Submit => self.submit(),
+            Action::ConvertSpan => self.composer.convert_span(),
+            Action::MoveCursor(Move::Right) => self.composer.move_cursor_right(),
+            Action::MoveCursor(Move::Left) => self.composer.move_cursor_left(),
+            Action::ExtendSelection(Move::Right) => self.composer.extend_selection(),
+            Action::ExtendSelection(Move::Left) => self.composer.shrink_selection(),
+            Action::MoveCursor(Move::Up) => self.composer.previous_candidate(),
+            Action::MoveCursor(Move::Down) => self.composer.next_candidate(),
+            Action::Input(value) => self.composer.input(value),
+            Action::DeleteChar => self.composer.delete_char(),
+            Action::DeleteWord => self.composer.delete_word(),
+            Action::ClearInput => self.clear_input(),
+            Action::MarkKnown => self.mark_known(),
+            Action::MarkHard => {
+                self.progress.toggle_mark(self.question_key(), Mark::Hard);
+            }
+            Action::FlagBadData => {
+                let note = self.composer.get_romanji().trim().to_string();
+                let note = (!note.is_empty()).then_some(note);
+                self.progress
+                    .toggle_mark(self.question_key(), Mark::BadData { note });
+                self.clear_input();
+            }
+            Action::ToggleFurigana => self.toggle_furigana(),
+            _ => {}
+        }
+        Transition::Stay
+    }
+
+    // a known phrase is skipped straight away
+    fn mark_known(&mut self) {
+        if self.progress.toggle_mark(self.question_key(), Mark::Known) {
+            self.next_question();
+            self.clear_input();
+        }
+    }
+
+    // progress store key of the asked phrase
+    pub fn question_key(&self) -> String {
+        let (_, _, phrase) = self.question();
+        self.session_key(phrase)
+    }
+
+    fn submit(&mut self) {
+        let answer = match (self.context.drill, self.context.translation_direction) {
+            (Drill::Translate, TranslationDirection::ToEN) => self.composer.get_romanji(),
+            (Drill::Translate, TranslationDirection::ToJP) => self.composer.get_kanji(),
+            (Drill::Reading | Drill::KanjiReading, _) => self.composer.get_kana(),
+            (Drill::Writing, _) => self.composer.get_kanji(),
+            (Drill::Kana, TranslationDirection::ToEN) => self.composer.get_romanji(),
+            (Drill::Kana, TranslationDirection::ToJP) => self.composer.get_kana(),
+        };
+        let (_, _, phrase) = self.question();
+        let correct = match self.context.drill {
+            Drill::Translate => {
+                grading::is_correct(phrase, self.context.translation_direction, &answer)
+            }
+            Drill::Reading | Drill::KanjiReading => grading::is_correct_reading(phrase, &answer),
+            Drill::Writing => grading::is_correct_writing(phrase, &answer),
+            Drill::Kana => grading::is_correct_kana(
+                phrase,
+                self.context.translation_direction,
+                &answer,
+                self.composer.kana_converter(),
+            ),
+        };
+        self.progress.record(self.question_key(), correct);
+
+        self.context.prev_pool_idx = Some(self.context.pool_idx);
+        self.context.prev_phrase_idx = Some(self.context.phrase_idx);
+        self.context.prev_translation_direction = Some(self.context.translation_direction);
+        self.context.prev_answer = Some(answer);
+        self.context.prev_correct = Some(correct);
+        self.context.answered += 1;
+        if !self.context.session_finished() {
+            self.next_question();
+        }
+        self.clear_input();
+    }
+
+    fn end_session(&mut self) {
+        self.context.current_screen = CurrentScreen::LessonSelect;
+        self.context.current_selection = CurrentSelection::Lesson;
+        self.context.lesson_idx = 0;
+        self.context.section_idx = None;
+        self.context.prev_pool_idx = None;
+        self.context.prev_phrase_idx = None;
+        self.context.prev_translation_direction = None;
+        self.context.prev_answer = None;
+        self.context.prev_correct = None;
+        self.context.asked_questions.clear();
+        self.context.drill_book = None;
+        self.clear_input();
+    }
+
+    fn clear_input(&mut self) {
+        self.composer.clear();
+    }
+
+    pub fn toggle_lesson(&mut self) {
+        let lesson_idx = self.context.lesson_idx;
+        if !self.context.selected_lessons.remove(&lesson_idx) {
+            self.context.selected_lessons.insert(lesson_idx);
+        }
+    }
+
+    pub fn toggle_section(&mut self) {
+        let section = (
+            self.context.lesson_idx,
+            self.context.section_idx.expect("section index not set"),
+        );
+        if !self.context.selected_sections.remove(&section) {
+            self.context.selected_sections.insert(section);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.context.selected_lessons.clear();
+        self.context.selected_sections.clear();
+    }
+
+    // sections practiced by a session, the highlighted lesson or section
+    // is used when nothing is selected
+    pub fn session_pool(&self) -> Vec<(usize, usize)> {
+        let mut pool = BTreeSet::<(usize, usize)>::new();
+        if self.context.has_selection() {
+            for &lesson_idx in &self.context.selected_lessons {
+                assert!(lesson_idx < self.book.lessons.len());
+                let lesson = &self.book.lessons[lesson_idx];
+                pool.extend(
+                    (0..lesson.sections.len()).map(|section_idx| (lesson_idx, section_idx)),
+                );
+            }
+            pool.extend(self.context.selected_sections.iter().copied());
+        } else if let Some(section_idx) = self.context.section_idx {
+            pool.insert((self.context.lesson_idx, section_idx));
+        } else {
+            assert!(self.context.lesson_idx < self.book.lessons.len());
+            let lesson = &self.book.lessons[self.context.lesson_idx];
+            pool.extend(
+                (0..lesson.sections.len())
+                    .map(|section_idx| (self.context.lesson_idx, section_idx)),
+            );
+        }
+        // sections without phrases cant be asked
+        pool.into_iter()
+            .filter(|&(lesson_idx, section_idx)| {
+                !self.book.lessons[lesson_idx].sections[section_idx]
+                    .phrases
+                    .is_empty()
+            })
+            .collect()
+    }
+
+    // returns false if there is nothing to practice
+    pub fn start_session(&mut self) -> bool {
+        self.start_pool(self.session_pool())
+    }
+
+    // start a session over the given (lesson index, section index) pairs
+    pub fn start_pool(&mut self, pool: Vec<(usize, usize)>) -> bool {
+        let (drill_book, pool) = match self.context.drill {
+            Drill::Translate => (None, pool),
+            drill => {
+                let (drill_book, pool) = self.drill_book(drill, &pool);
+                (Some(drill_book), pool)
+            }
+        };
+        if pool.is_empty() {
+            return false;
+        }
+        self.context.drill_book = drill_book;
+        self.context.asked_questions.clear();
+        self.context.hard_asked.clear();
+        self.context.pool = pool;
+        self.context.answered = 0;
+        self.context.rng = StdRng::seed_from_u64(self.context.seed);
+        self.context.prev_pool_idx = None;
+        self.context.prev_phrase_idx = None;
+        self.context.prev_translation_direction = None;
+        self.context.prev_answer = None;
+        self.context.prev_correct = None;
+        self.context.current_screen = CurrentScreen::Review;
+        self.next_question();
+        true
+    }
+
+    pub fn next_question(&mut self) {
+        let rng = &mut self.context.rng;
+        self.context.translation_direction = self
+            .context
+            .direction_mode
+            .pick(self.context.prev_translation_direction, rng);
+
+        assert!(!self.context.pool.is_empty());
+        // known phrases are skipped, unless every phrase in the pool is known
+        let skip_known = (0..self.context.pool.len()).any(|pool_idx| {
+            (0..self.pool_section(pool_idx).phrases.len())
+                .any(|phrase_idx| !self.pool_phrase_marked(pool_idx, phrase_idx, &Mark::Known))
         });
-        // generate kanji from kana and offsets
-        if self.kanji.chars().count() > 0 {
-            // sort to keep adjusted offset valid
-            self.kanji_offsets.sort();
-            let mut offset_adjust: usize = 0;
-            // update kanji text
-            for kanji_offset in &self.kanji_offsets {
-                assert!(kanji_offset.1 >= 1);
-                let start = kanji_offset.0;
-                let end = kanji_offset.0 + kanji_offset.1;
-                let kanji_list_offset = kanji_offset.2;
-                assert!(start <= self.kana.chars().count() && end <= self.kana.chars().count());
-                let kana_substr: String = self.kana.chars().take(end).skip(start).collect();
-                let kanji_list = self.kanji_converter.convert(&kana_substr);
-                if kanji_list_offset < kanji_list.len() {
-                    let kanji_char = String::from(kanji_list[kanji_list_offset]);
-                    let start_byte_index = self
-                        .kanji
-                        .char_indices()
-                        .nth(start - offset_adjust)
-                        .map(|(i, _)| i)
-                        .unwrap_or(self.kanji.len());
-                    let end_byte_index = self
-                        .kanji
-                        .char_indices()
-                        .nth(end - offset_adjust)
-                        .map(|(i, _)| i)
-                        .unwrap_or(self.kanji.len());
-                    self.kanji
-                        .replace_range(start_byte_index..end_byte_index, &kanji_char);
-                    // adjust for shorter len kanji than hiragana after swap
-                    offset_adjust += kanji_offset.1 - 1;
+        // only pick sections with unasked phrases, start over once all are asked
+        let mut remaining: Vec<usize> = (0..self.context.pool.len())
+            .filter(|&pool_idx| !self.unasked_phrases(pool_idx, skip_known).is_empty())
+            .collect();
+        if remaining.is_empty() {
+            self.context.asked_questions.clear();
+            self.context.hard_asked.clear();
+            remaining = (0..self.context.pool.len())
+                .filter(|&pool_idx| !self.unasked_phrases(pool_idx, skip_known).is_empty())
+                .collect();
+        }
+        let pool_idx = remaining[self.context.rng.gen_range(0..remaining.len())];
+
+        let unasked = self.unasked_phrases(pool_idx, skip_known);
+        let phrase_idx = unasked[self.context.rng.gen_range(0..unasked.len())];
+        // hard phrases are asked twice every round
+        let id = self.pool_section(pool_idx).phrases[phrase_idx].id.clone();
+        let asked_twice = !self.pool_phrase_marked(pool_idx, phrase_idx, &Mark::Hard)
+            || !self.context.hard_asked.insert(id.clone());
+        if asked_twice {
+            self.context.asked_questions.insert(id);
+        }
+
+        self.context.pool_idx = pool_idx;
+        self.context.phrase_idx = phrase_idx;
+    }
+
+    fn unasked_phrases(&self, pool_idx: usize, skip_known: bool) -> Vec<usize> {
+        let phrases = &self.pool_section(pool_idx).phrases;
+        (0..phrases.len())
+            .filter(|&phrase_idx| {
+                !self
+                    .context
+                    .asked_questions
+                    .contains(&phrases[phrase_idx].id)
+            })
+            .filter(|&phrase_idx| {
+                !skip_known || !self.pool_phrase_marked(pool_idx, phrase_idx, &Mark::Known)
+            })
+            .collect()
+    }
+
+    fn pool_phrase_marked(&self, pool_idx: usize, phrase_idx: usize, mark: &Mark) -> bool {
+        let phrase = &self.pool_section(pool_idx).phrases[phrase_idx];
+        self.progress.is_marked(&self.session_key(phrase), mark)
+    }
+
+    // the questions of a drill and a pool of every section of them. the
+    // kana drill asks the kana of its pools instead of the chosen sections
+    fn drill_book(&self, drill: Drill, pool: &[(usize, usize)]) -> (Book, Vec<(usize, usize)>) {
+        let lessons = match drill {
+            Drill::Kana => {
+                kana_drill::lessons(self.composer.kana_converter(), &self.context.kana_pools)
+            }
+            drill => self.drill_lessons(drill, pool),
+        };
+        let pool = lessons
+            .iter()
+            .enumerate()
+            .flat_map(|(lesson_idx, lesson)| {
+                (0..lesson.sections.len()).map(move |section_idx| (lesson_idx, section_idx))
+            })
+            .collect();
+        let drill_book = Book {
+            title: None,
+            source: None,
+            lessons,
+        };
+        (drill_book, pool)
+    }
+
+    // the questions of every pooled section, each in a lesson of its own.
+    // single kanji are asked once per session, in the first section they
+    // appear in
+    fn drill_lessons(&self, drill: Drill, pool: &[(usize, usize)]) -> Vec<Lesson> {
+        let mut lessons = Vec::<Lesson>::new();
+        let mut asked_kanji = HashSet::<char>::new();
+        for &(lesson_idx, section_idx) in pool {
+            let lesson = &self.book[lesson_idx];
+            let section = &lesson.sections[section_idx];
+            let with_kanji = section
+                .phrases
+                .iter()
+                .filter(|phrase| phrase.kanji.is_some());
+            let phrases: Vec<Phrase> = match drill {
+                Drill::Translate | Drill::Kana => section.phrases.clone(),
+                // reading and writing progress is kept apart from translating
+                Drill::Reading | Drill::Writing => with_kanji
+                    .map(|phrase| Phrase {
+                        id: match drill {
+                            Drill::Reading => format!("reading:{}", phrase.id),
+                            _ => format!("writing:{}", phrase.id),
+                        },
+                        ..phrase.clone()
+                    })
+                    .collect(),
+                Drill::KanjiReading => {
+                    let words: Vec<&str> = with_kanji
+                        .filter_map(|phrase| phrase.kanji.as_deref())
+                        .collect();
+                    let mut phrases = Vec::<Phrase>::new();
+                    for kanji in words.iter().flat_map(|word| word.chars()) {
+                        let readings = self.furigana.readings(kanji);
+                        if readings.is_empty() || !asked_kanji.insert(kanji) {
+                            continue;
+                        }
+                        let used_in: Vec<&str> = words
+                            .iter()
+                            .copied()
+                            .filter(|word| word.contains(kanji))
+                            .collect();
+                        phrases.push(Phrase {
+                            id: format!("kanji:{kanji}"),
+                            en: used_in.join(", "),
+                            jp: readings.join("; "),
+                            kanji: Some(kanji.to_string()),
+                            ..Phrase::default()
+                        });
+                    }
+                    phrases
                 }
+            };
+            if !phrases.is_empty() {
+                lessons.push(Lesson {
+                    sections: vec![Section {
+                        name: section.name.clone(),
+                        phrases,
+                    }],
+                    ..lesson.clone()
+                });
             }
+        }
+        lessons
+    }
+
+    // the book the pool points into
+    fn session_book(&self) -> &Book {
+        self.context.drill_book.as_ref().unwrap_or(&self.book)
+    }
+
+    pub fn pool_section(&self, pool_idx: usize) -> &Section {
+        assert!(pool_idx < self.context.pool.len());
+        let (lesson_idx, section_idx) = self.context.pool[pool_idx];
+        &self.session_book()[lesson_idx].sections[section_idx]
+    }
+
+    pub fn question(&self) -> (&Lesson, &Section, &Phrase) {
+        assert!(self.context.pool_idx < self.context.pool.len());
+        let (lesson_idx, section_idx) = self.context.pool[self.context.pool_idx];
+        let lesson = &self.session_book()[lesson_idx];
+        assert!(section_idx < lesson.sections.len());
+        let section = &lesson.sections[section_idx];
+        assert!(self.context.phrase_idx < section.phrases.len());
+        (lesson, section, &section.phrases[self.context.phrase_idx])
+    }
+
+    pub fn prev_question(&self) -> Option<&Phrase> {
+        let prev_phrase_idx = self.context.prev_phrase_idx?;
+        let section = self.pool_section(
+            self.context
+                .prev_pool_idx
+                .expect("previous pool index not set"),
+        );
+        assert!(prev_phrase_idx < section.phrases.len());
+        Some(&section.phrases[prev_phrase_idx])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tests::add_deck;
+
+    #[test]
+    fn test_direction_mode() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let test_cases = vec![
+            (DirectionMode::ToJP, None, TranslationDirection::ToJP),
+            (
+                DirectionMode::ToJP,
+                Some(TranslationDirection::ToJP),
+                TranslationDirection::ToJP,
+            ),
+            (DirectionMode::ToEN, None, TranslationDirection::ToEN),
+            (DirectionMode::Alternate, None, TranslationDirection::ToEN),
+            (
+                DirectionMode::Alternate,
+                Some(TranslationDirection::ToEN),
+                TranslationDirection::ToJP,
+            ),
+            (
+                DirectionMode::Alternate,
+                Some(TranslationDirection::ToJP),
+                TranslationDirection::ToEN,
+            ),
+            (
+                DirectionMode::Random { to_jp_percent: 0 },
+                None,
+                TranslationDirection::ToEN,
+            ),
+            (
+                DirectionMode::Random { to_jp_percent: 100 },
+                None,
+                TranslationDirection::ToJP,
+            ),
+        ];
+
+        for (mode, prev_direction, expected) in test_cases {
+            for _ in 0..20 {
+                let result = mode.pick(prev_direction, &mut rng);
+                assert_eq!(result, expected, "Failed for mode: {:?}", mode);
+            }
+        }
+
+        let mode = DirectionMode::default().adjust_weight(80);
+        assert_eq!(mode, DirectionMode::Random { to_jp_percent: 100 });
+        assert_eq!(DirectionMode::ToJP.adjust_weight(10), DirectionMode::ToJP);
+    }
+
+    #[test]
+    fn test_session_pool() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), None).unwrap();
+        let lesson_len = app.book[1].sections.len();
+        assert_eq!(app.session_pool().len(), app.book[0].sections.len());
+
+        app.context.lesson_idx = 1;
+        app.toggle_lesson();
+        app.context.lesson_idx = 3;
+        app.context.section_idx = Some(2);
+        app.toggle_section();
+        // sections of selected lessons are not repeated
+        app.context.lesson_idx = 1;
+        app.context.section_idx = Some(0);
+        app.toggle_section();
+        let pool = app.session_pool();
+        assert_eq!(pool.len(), lesson_len + 1);
+        assert!(pool.contains(&(3, 2)));
+        assert!(pool.contains(&(1, 0)));
+
+        app.toggle_section();
+        app.context.lesson_idx = 1;
+        app.context.section_idx = None;
+        app.toggle_lesson();
+        assert_eq!(app.session_pool(), vec![(3, 2)]);
+
+        // every phrase is asked once before any repeats
+        assert!(app.start_session());
+        let phrase_count = app.book[3].sections[2].phrases.len();
+        let mut asked = HashSet::<usize>::new();
+        for _ in 0..phrase_count {
+            assert_eq!(app.context.pool[app.context.pool_idx], (3, 2));
+            assert!(asked.insert(app.context.phrase_idx));
+            app.next_question();
+        }
+        assert_eq!(asked.len(), phrase_count);
+    }
+
+    fn session_questions(app: &mut App, count: usize) -> Vec<(usize, usize, TranslationDirection)> {
+        let mut questions = Vec::new();
+        for _ in 0..count {
+            questions.push((
+                app.context.pool_idx,
+                app.context.phrase_idx,
+                app.context.translation_direction,
+            ));
+            app.next_question();
+        }
+        questions
+    }
+
+    #[test]
+    fn test_seeded_session() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(42)).unwrap();
+        let mut other = App::new(&Resources::default(), Preferences::default(), Some(42)).unwrap();
+        for app in [&mut app, &mut other] {
+            app.context.lesson_idx = 1;
+            assert!(app.start_session());
+        }
+        let questions = session_questions(&mut app, 100);
+        assert_eq!(questions, session_questions(&mut other, 100));
 
+        // restarting a session replays the same order
+        assert!(app.start_session());
+        assert_eq!(questions, session_questions(&mut app, 100));
+
+        // no phrase repeats until every phrase in the pool was asked
+        let phrase_count: usize = (0..app.context.pool.len())
+            .map(|pool_idx| app.pool_section(pool_idx).phrases.len())
+            .sum();
+        let asked: HashSet<(usize, usize)> = questions
+            .iter()
+            .take(phrase_count)
+            .map(|&(pool_idx, phrase_idx, _)| (pool_idx, phrase_idx))
+            .collect();
+        assert_eq!(asked.len(), phrase_count.min(100));
+
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(7)).unwrap();
+        app.context.lesson_idx = 1;
+        assert!(app.start_session());
+        assert_ne!(questions, session_questions(&mut app, 100));
+    }
+
+    fn type_romanji(app: &mut App, romanji: &str) {
+        for value in romanji.chars() {
+            app.handle_event(Action::Input(value));
+        }
+    }
+
+    #[test]
+    fn test_screen_transitions() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        assert_eq!(
+            app.handle_event(Action::Continue),
+            Transition::Screen(CurrentScreen::LessonSelect)
+        );
+        assert_eq!(
+            app.handle_event(Action::MoveCursor(Move::Down)),
+            Transition::Stay
+        );
+        assert_eq!(
+            app.handle_event(Action::StartSession),
+            Transition::Screen(CurrentScreen::Review)
+        );
+        assert_eq!(app.context.pool.len(), app.book[1].sections.len());
+        assert_eq!(
+            app.handle_event(Action::Back),
+            Transition::Screen(CurrentScreen::LessonSelect)
+        );
+        assert_eq!(app.context.lesson_idx, 0);
+        assert_eq!(app.handle_event(Action::Back), Transition::Quit);
+
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        assert_eq!(app.handle_event(Action::Back), Transition::Quit);
+    }
+
+    #[test]
+    fn test_lesson_cursor() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.handle_event(Action::Continue);
+        let lesson_count = app.book.lessons.len();
+
+        app.handle_event(Action::MoveCursor(Move::Up));
+        assert_eq!(app.context.lesson_idx, lesson_count - 1);
+        app.handle_event(Action::MoveCursor(Move::Down));
+        assert_eq!(app.context.lesson_idx, 0);
+        app.handle_event(Action::MoveCursor(Move::Down));
+        app.handle_event(Action::ToggleSelection);
+        assert!(app.context.selected_lessons.contains(&1));
+
+        app.handle_event(Action::MoveCursor(Move::Right));
+        assert_eq!(app.context.current_selection, CurrentSelection::Section);
+        assert_eq!(app.context.section_idx, Some(0));
+        app.handle_event(Action::MoveCursor(Move::Up));
+        let section_count = app.book[1].sections.len();
+        assert_eq!(app.context.section_idx, Some(section_count - 1));
+        app.handle_event(Action::ToggleSelection);
+        assert!(
+            app.context
+                .selected_sections
+                .contains(&(1, section_count - 1))
+        );
+
+        app.handle_event(Action::ClearSelection);
+        assert!(!app.context.has_selection());
+        app.handle_event(Action::MoveCursor(Move::Left));
+        assert_eq!(app.context.current_selection, CurrentSelection::Lesson);
+        assert_eq!(app.context.section_idx, None);
+
+        app.handle_event(Action::CycleDirectionMode);
+        assert_eq!(app.context.direction_mode, DirectionMode::ToJP);
+        assert_eq!(app.preferences.direction_mode, DirectionMode::ToJP);
+    }
+
+    #[test]
+    fn test_review_input() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.set_direction_mode(DirectionMode::ToJP);
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::StartSession);
+
+        type_romanji(&mut app, "nihon");
+        assert_eq!(app.composer.kana, "にほん");
+        assert_eq!(app.composer.highlighted_kanji, vec!['二', '日']);
+        app.handle_event(Action::MoveCursor(Move::Down));
+        app.handle_event(Action::ConvertSpan);
+        assert_eq!(app.composer.kanji, "日ほん");
+
+        app.handle_event(Action::MoveCursor(Move::Right));
+        app.handle_event(Action::ExtendSelection(Move::Right));
+        assert_eq!((app.composer.kana_offset, app.composer.kana_len), (1, 2));
+        assert_eq!(app.composer.kanji_offset, 0);
+        assert_eq!(app.composer.highlighted_kanji, vec!['本']);
+        app.handle_event(Action::ConvertSpan);
+        assert_eq!(app.composer.kanji, "日本");
+
+        // removing highlighted kana shrinks the highlight
+        app.handle_event(Action::DeleteChar);
+        assert_eq!(app.composer.kana, "にほ");
+        assert_eq!((app.composer.kana_offset, app.composer.kana_len), (1, 1));
+        assert_eq!(app.composer.kanji, "日ほ");
+        type_romanji(&mut app, "n");
+
+        let question = (app.context.pool_idx, app.context.phrase_idx);
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_answer, Some(String::from("日ほん")));
+        assert_eq!(
+            (app.context.prev_pool_idx, app.context.prev_phrase_idx),
+            (Some(question.0), Some(question.1))
+        );
+        assert!(
+            app.composer.romanji.is_empty()
+                && app.composer.kana.is_empty()
+                && app.composer.kanji.is_empty()
+        );
+        assert_eq!((app.composer.kana_offset, app.composer.kana_len), (0, 1));
+    }
+
+    #[test]
+    fn test_delete_word() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::StartSession);
+
+        type_romanji(&mut app, "ohayou gozaimasu  ");
+        app.handle_event(Action::DeleteWord);
+        assert_eq!(app.composer.romanji, "ohayou ");
+        assert_eq!(app.composer.kana, "おはよう ");
+        app.handle_event(Action::DeleteWord);
+        assert!(app.composer.romanji.is_empty() && app.composer.kana.is_empty());
+        assert_eq!((app.composer.kana_offset, app.composer.kana_len), (0, 1));
+
+        type_romanji(&mut app, "konnichiha");
+        app.handle_event(Action::ClearInput);
+        assert!(
+            app.composer.romanji.is_empty()
+                && app.composer.kana.is_empty()
+                && app.composer.kanji.is_empty()
+        );
+        assert_eq!(app.handle_event(Action::Quit), Transition::Quit);
+    }
+
+    #[test]
+    fn test_session_len() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.context.session_len = Some(2);
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::StartSession);
+
+        app.handle_event(Action::Submit);
+        assert!(!app.context.session_finished());
+        app.handle_event(Action::Submit);
+        assert!(app.context.session_finished());
+        // input is ignored until the session is left
+        app.handle_event(Action::Input('a'));
+        assert!(app.composer.romanji.is_empty());
+        assert_eq!(
+            app.handle_event(Action::Submit),
+            Transition::Screen(CurrentScreen::LessonSelect)
+        );
+
+        app.handle_event(Action::StartSession);
+        assert_eq!(app.context.answered, 0);
+        assert!(!app.context.session_finished());
+    }
+
+    #[test]
+    fn test_browse() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::MoveCursor(Move::Down));
+        assert_eq!(
+            app.handle_event(Action::BrowseVocabulary),
+            Transition::Screen(CurrentScreen::Browse)
+        );
+        let phrase_count: usize = app.book[1]
+            .sections
+            .iter()
+            .map(|section| section.phrases.len())
+            .sum();
+        assert_eq!(app.browse_rows().len(), phrase_count);
+
+        app.handle_event(Action::MoveCursor(Move::Up));
+        assert_eq!(app.context.browse_idx, 0);
+        app.handle_event(Action::MoveCursor(Move::Down));
+        assert_eq!(app.context.browse_idx, 1);
+
+        // english, romanji and kana all find the same phrase
+        let test_cases = vec!["student", "GAKUSEI", "がくせい", "gakusei"];
+        for query in test_cases {
+            app.handle_event(Action::ClearInput);
+            for value in query.chars() {
+                app.handle_event(Action::Input(value));
+            }
+            let rows = app.browse_rows();
             assert!(
-                self.kana_offset < self.kana.chars().count()
-                    && self.kana_offset + self.kana_len <= self.kana.chars().count()
+                rows.iter().any(|&(lesson_idx, section_idx, phrase_idx)| {
+                    app.book[lesson_idx].sections[section_idx].phrases[phrase_idx].jp == "がくせい"
+                }),
+                "Failed for query: {}",
+                query
+            );
+            assert!(rows.len() < phrase_count, "Failed for query: {}", query);
+            assert_eq!(app.context.browse_idx, 0);
+        }
+
+        app.handle_event(Action::Back);
+        assert_eq!(app.context.current_screen, CurrentScreen::LessonSelect);
+        // only the highlighted section is browsed
+        app.handle_event(Action::MoveCursor(Move::Right));
+        app.handle_event(Action::BrowseVocabulary);
+        assert!(app.context.browse_query.is_empty());
+        assert_eq!(
+            app.browse_rows().len(),
+            app.book[1].sections[0].phrases.len()
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.handle_event(Action::Continue);
+        assert_eq!(
+            app.handle_event(Action::SearchVocabulary),
+            Transition::Screen(CurrentScreen::Search)
+        );
+        assert!(app.search_rows().is_empty());
+
+        // english, romanji, kana and kanji all find the phrase
+        let test_cases = vec!["japan", "nihon", "ニホン", "日本"];
+        for query in test_cases {
+            app.handle_event(Action::ClearInput);
+            for value in query.chars() {
+                app.handle_event(Action::Input(value));
+            }
+            assert!(
+                app.search_rows()
+                    .iter()
+                    .any(|&(lesson_idx, section_idx, phrase_idx)| {
+                        app.book[lesson_idx].sections[section_idx].phrases[phrase_idx].jp
+                            == "にほん"
+                    }),
+                "Failed for query: {}",
+                query
             );
-            let kana_substr: String = self
-                .kana
-                .chars()
-                .take(self.kana_offset + self.kana_len)
-                .skip(self.kana_offset)
-                .collect();
-            self.highlighted_kanji = self.kanji_converter.convert(&kana_substr);
-        } else {
-            self.highlighted_kanji.clear();
         }
+
+        // hits come from every lesson
+        app.handle_event(Action::ClearInput);
+        for value in "time".chars() {
+            app.handle_event(Action::Input(value));
+        }
+        let rows = app.search_rows();
+        assert!(
+            rows.iter()
+                .any(|&(lesson_idx, _, _)| lesson_idx != rows[0].0)
+        );
+
+        // enter drills the section of the highlighted hit
+        app.handle_event(Action::MoveCursor(Move::Down));
+        let (lesson_idx, section_idx, _) = rows[1];
+        assert_eq!(
+            app.handle_event(Action::StartSession),
+            Transition::Screen(CurrentScreen::Review)
+        );
+        assert_eq!(app.context.pool, vec![(lesson_idx, section_idx)]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.set_direction_mode(DirectionMode::ToJP);
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::StartSession);
+
+        let key = app.question_key();
+        let (_, _, phrase) = app.question();
+        let answer = phrase.jp.clone();
+        app.composer.kanji = answer;
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+        assert_eq!(app.progress.get(&key).streak, 1);
+
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(false));
+        assert_eq!(app.progress.phrases.len(), 2);
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.context.lesson_idx = 1;
+        app.context.section_idx = Some(0);
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::StartSession);
+        let phrase_count = app.pool_section(0).phrases.len();
+
+        // a known phrase is skipped right away and not asked again
+        let known = app.context.phrase_idx;
+        app.handle_event(Action::MarkKnown);
+        assert_ne!(app.context.phrase_idx, known);
+        let hard = app.context.phrase_idx;
+        app.handle_event(Action::MarkHard);
+        assert!(app.progress.is_marked(&app.question_key(), &Mark::Hard));
+
+        // finish the first round, then every round asks the hard phrase twice
+        for _ in 0..phrase_count - 2 {
+            app.next_question();
+        }
+        let mut counts = vec![0; phrase_count];
+        for _ in 0..phrase_count * 3 {
+            app.next_question();
+            counts[app.context.phrase_idx] += 1;
+        }
+        assert_eq!(counts[known], 0);
+        assert_eq!(counts[hard], 6);
+        assert!(
+            counts
+                .iter()
+                .enumerate()
+                .all(|(phrase_idx, &count)| phrase_idx == known
+                    || phrase_idx == hard
+                    || count == 3)
+        );
+
+        // the typed answer is the note, flagging again removes the flag
+        type_romanji(&mut app, "typo in kanji");
+        app.handle_event(Action::FlagBadData);
+        assert_eq!(
+            app.progress.mark(&app.question_key()),
+            Some(&Mark::BadData {
+                note: Some(String::from("typo in kanji"))
+            })
+        );
+        assert!(app.composer.romanji.is_empty());
+        app.handle_event(Action::FlagBadData);
+        assert_eq!(app.progress.mark(&app.question_key()), None);
+
+        // with every phrase known they are asked anyway
+        app.handle_event(Action::Back);
+        app.context.lesson_idx = 0;
+        app.context.section_idx = Some(0);
+        app.handle_event(Action::StartSession);
+        for _ in 0..app.pool_section(0).phrases.len() {
+            let key = app.question_key();
+            if !app.progress.is_marked(&key, &Mark::Known) {
+                app.progress.toggle_mark(key, Mark::Known);
+            }
+            app.next_question();
+        }
+        app.next_question();
+        assert!(app.progress.is_marked(&app.question_key(), &Mark::Known));
     }
 
-    pub fn get_romanji(&self) -> String {
-        return self.romanji.clone();
+    #[test]
+    fn test_book_select() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        add_deck(
+            &mut app.library,
+            r#"[{"id": "person", "en": "person", "jp": "ひと"}]"#,
+        );
+        assert_eq!(
+            app.handle_event(Action::Continue),
+            Transition::Screen(CurrentScreen::BookSelect)
+        );
+        app.handle_event(Action::MoveCursor(Move::Down));
+        app.handle_event(Action::MoveCursor(Move::Down));
+        assert_eq!(app.context.shelf_idx, 1);
+        assert_eq!(
+            app.handle_event(Action::Continue),
+            Transition::Screen(CurrentScreen::LessonSelect)
+        );
+        assert_eq!(app.book.title.as_deref(), Some("JLPT N5"));
+        // decks keep their progress apart from the lessons file
+        assert_eq!(
+            app.progress_key(&app.book[0].sections[0].phrases[0]),
+            "jlpt-n5.json:person"
+        );
+        app.handle_event(Action::ToggleSelection);
+        assert!(app.context.has_selection());
+
+        // back to the books and into the shipped book again
+        assert_eq!(
+            app.handle_event(Action::Back),
+            Transition::Screen(CurrentScreen::BookSelect)
+        );
+        app.handle_event(Action::MoveCursor(Move::Up));
+        app.handle_event(Action::Continue);
+        assert_eq!(app.book.title.as_deref(), Some("Genki I"));
+        assert!(!app.context.has_selection());
+        assert_eq!(
+            app.progress_key(&app.book[0].sections[0].phrases[0]),
+            "0/0/0"
+        );
+        app.handle_event(Action::Back);
+        assert_eq!(app.handle_event(Action::Back), Transition::Quit);
     }
 
-    pub fn get_kana(&self) -> String {
-        return self.kana.clone();
+    #[test]
+    fn test_phrase_ids() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        // the phrase moved to the front and got a readable id
+        add_deck(
+            &mut app.library,
+            r#"[
+                {"id": "person", "former_ids": ["1/0/1"], "en": "person", "jp": "ひと"},
+                {"id": "1/0/0", "en": "friend", "jp": "ともだち"}
+            ]"#,
+        );
+        app.progress
+            .record(String::from("jlpt-n5.json:1/0/1"), true);
+        app.progress
+            .toggle_mark(String::from("jlpt-n5.json:1/0/1"), Mark::Known);
+        app.migrate_progress();
+        assert_eq!(app.progress.get("jlpt-n5.json:person").seen, 1);
+        assert!(app.progress.is_marked("jlpt-n5.json:person", &Mark::Known));
+        assert_eq!(app.progress.get("jlpt-n5.json:1/0/1").seen, 0);
+
+        // the known phrase is skipped wherever it is in the section
+        app.open_shelf(1);
+        app.context.current_screen = CurrentScreen::LessonSelect;
+        app.context.section_idx = Some(0);
+        app.handle_event(Action::StartSession);
+        for _ in 0..3 {
+            assert_eq!(app.question().2.id, "1/0/0");
+            app.next_question();
+        }
+        assert_eq!(app.context.asked_questions.len(), 1);
+        assert!(app.context.asked_questions.contains("1/0/0"));
+    }
+
+    #[test]
+    fn test_drills() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.context.current_screen = CurrentScreen::LessonSelect;
+        app.context.lesson_idx = 3;
+        app.handle_event(Action::CycleDrill);
+        assert_eq!(app.context.drill, Drill::Reading);
+        assert_eq!(app.preferences.drill, Drill::Reading);
+
+        // only phrases with kanji, kept apart from translating
+        app.handle_event(Action::StartSession);
+        let phrase_count = app
+            .session_pool()
+            .iter()
+            .map(|&(lesson_idx, section_idx)| {
+                app.book[lesson_idx].sections[section_idx]
+                    .phrases
+                    .iter()
+                    .filter(|phrase| phrase.kanji.is_some())
+                    .count()
+            })
+            .sum::<usize>();
+        let mut asked = HashSet::<String>::new();
+        for _ in 0..phrase_count {
+            let (_, _, phrase) = app.question();
+            assert!(phrase.kanji.is_some());
+            assert!(phrase.id.starts_with("reading:"));
+            asked.insert(phrase.id.clone());
+            app.next_question();
+        }
+        assert_eq!(asked.len(), phrase_count);
+
+        let (_, _, phrase) = app.question();
+        let (key, jp) = (app.question_key(), phrase.jp.clone());
+        app.composer.kana = jp;
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+        assert_eq!(app.progress.get(&key).correct, 1);
+
+        // every kanji once, any of its readings counts
+        app.handle_event(Action::Back);
+        app.handle_event(Action::CycleDrill);
+        app.context.lesson_idx = 1;
+        app.handle_event(Action::StartSession);
+        let mut kanji = HashSet::<String>::new();
+        loop {
+            let (_, _, phrase) = app.question();
+            if !kanji.insert(phrase.kanji.clone().unwrap()) {
+                break;
+            }
+            assert_eq!(
+                phrase.id,
+                format!("kanji:{}", phrase.kanji.as_ref().unwrap())
+            );
+            app.next_question();
+        }
+        assert!(kanji.contains("日"));
+        while app.question().2.kanji.as_deref() != Some("日") {
+            app.next_question();
+        }
+        assert!(app.question().2.en.contains("日本"));
+        app.composer.kana = String::from("ひ");
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+        assert_eq!(app.progress.get("kanji:日").seen, 1);
+
+        // the kanji picked for the kana, kept apart from reading
+        app.handle_event(Action::Back);
+        app.handle_event(Action::CycleDrill);
+        assert_eq!(app.context.drill, Drill::Writing);
+        app.context.lesson_idx = 3;
+        app.handle_event(Action::StartSession);
+        let (_, _, phrase) = app.question();
+        assert!(phrase.id.starts_with("writing:"));
+        let (key, jp, kanji) = (app.question_key(), phrase.jp.clone(), phrase.kanji.clone());
+        app.composer.kana = jp;
+        app.composer.kanji = kanji.unwrap();
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+        assert_eq!(app.progress.get(&key).correct, 1);
+        let jp = app.question().2.jp.clone();
+        app.composer.kana = jp.clone();
+        app.composer.kanji = jp;
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(false));
+
+        // nothing to ask without kanji
+        app.handle_event(Action::Back);
+        app.context.lesson_idx = 0;
+        app.context.section_idx = Some(0);
+        assert!(!app.start_session());
+        assert_eq!(app.context.current_screen, CurrentScreen::LessonSelect);
+
+        // a kanji of a deck counts for the same kanji of the lessons
+        add_deck(
+            &mut app.library,
+            r#"[{"id": "japan", "en": "Japan", "jp": "にほん", "kanji": "日本"}]"#,
+        );
+        app.open_shelf(1);
+        app.context.drill = Drill::KanjiReading;
+        app.handle_event(Action::StartSession);
+        while app.question().2.kanji.as_deref() != Some("日") {
+            app.next_question();
+        }
+        assert_eq!(app.question_key(), "kanji:日");
+        app.composer.kana = String::from("にち");
+        app.handle_event(Action::Submit);
+        assert_eq!(app.progress.get("kanji:日").seen, 2);
+        assert_eq!(app.progress.get("kanji:日").correct, 2);
+    }
+
+    #[test]
+    fn test_kana_drill() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.context.current_screen = CurrentScreen::LessonSelect;
+        app.set_direction_mode(DirectionMode::ToEN);
+        // the first press picks the kana drill, the next ones the kana
+        app.handle_event(Action::CycleKanaPools);
+        assert_eq!(app.context.drill, Drill::Kana);
+        assert!(app.context.kana_pools.is_empty());
+        app.handle_event(Action::CycleKanaPools);
+        let pools = vec![KanaPool::Hiragana, KanaPool::Dakuten];
+        assert_eq!(app.context.kana_pools, pools);
+        assert_eq!(app.preferences.kana_pools, pools);
+
+        // every kana once a round, whatever sections are chosen
+        app.handle_event(Action::StartSession);
+        let mut asked = HashSet::<String>::new();
+        for _ in 0..71 {
+            let (lesson, _, phrase) = app.question();
+            assert_eq!(lesson.name_en, "Hiragana");
+            assert_eq!(phrase.id, format!("kana:{}", phrase.jp));
+            asked.insert(phrase.jp.clone());
+            app.next_question();
+        }
+        assert_eq!(asked.len(), 71);
+        assert!(asked.contains("ん") && asked.contains("ぽ"));
+
+        // the romanji of the kana, kept per kana
+        let (_, _, phrase) = app.question();
+        let (key, en) = (app.question_key(), phrase.en.clone());
+        app.composer.romanji = en;
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+        assert_eq!(app.progress.get(&key).correct, 1);
+
+        // the kana of the romanji, in the script asked for
+        app.handle_event(Action::Back);
+        app.set_direction_mode(DirectionMode::ToJP);
+        app.set_kana_pools(vec![KanaPool::Katakana]);
+        app.handle_event(Action::StartSession);
+        let jp = app.question().2.jp.clone();
+        app.composer.kana = to_hiragana(&jp);
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(false));
+        let jp = app.question().2.jp.clone();
+        app.composer.kana = jp.clone();
+        app.handle_event(Action::Submit);
+        assert_eq!(app.context.prev_correct, Some(true));
+
+        // a kana answered with a deck open counts for the same kana
+        let key = format!("kana:{jp}");
+        assert_eq!(app.progress.get(&key).correct, 1);
+        add_deck(
+            &mut app.library,
+            r#"[{"id": "person", "en": "person", "jp": "ひと"}]"#,
+        );
+        app.handle_event(Action::Back);
+        app.open_shelf(1);
+        app.handle_event(Action::StartSession);
+        while app.question().2.jp != jp {
+            app.next_question();
+        }
+        assert_eq!(app.question_key(), key);
+        app.composer.kana = jp;
+        app.handle_event(Action::Submit);
+        assert_eq!(app.progress.get(&key).seen, 2);
+        assert_eq!(app.progress.get(&key).correct, 2);
     }
 
-    pub fn get_kanji(&self) -> String {
-        return self.kanji.clone();
+    #[test]
+    fn test_cycle_theme() {
+        let mut app = App::new(&Resources::default(), Preferences::default(), Some(0)).unwrap();
+        app.colors.accent = Some(ratatui::style::Color::Cyan);
+        app.handle_event(Action::Continue);
+        app.handle_event(Action::CycleTheme);
+        assert_eq!(app.theme.name, ThemeName::Light);
+        assert_eq!(app.preferences.theme, ThemeName::Light);
+        // config colours survive switching themes
+        assert_eq!(app.theme.accent.fg, Some(ratatui::style::Color::Cyan));
     }
 }
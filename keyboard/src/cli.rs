@@ -0,0 +1,668 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::anki::{self, Deck};
+use crate::app::{App, CurrentScreen, DirectionMode, Drill};
+use crate::kana_converter::{RomanjiToKanaConverter, to_katakana};
+use crate::kana_drill::KanaPool;
+use crate::kanji_converter::{HiragaToKanjiConverter, best_guess};
+use crate::lessons::{Book, Issue};
+use crate::library::Shelf;
+use crate::progress::Progress;
+use crate::resources::Resources;
+use crate::tabular::{self, Delimiter};
+
+#[derive(Debug, Parser)]
+#[command(name = "keyboard", about = "Japanese keyboard to practice Genki vocab")]
+pub struct Cli {
+    /// Directory containing lessons.json, kana.json and kanji.json [default: resources]
+    #[arg(long, global = true)]
+    pub resources: Option<PathBuf>,
+
+    /// Config file to use instead of config.toml in the config directory
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Seed for the question order, the same seed gives the same quiz
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start practicing straight away, skipping the welcome screen
+    Practice(PracticeArgs),
+    /// Convert romaji lines read from stdin to kana
+    Convert(ConvertArgs),
+    /// Check the resource files for problems
+    Validate,
+    /// Print lesson and vocabulary statistics
+    Stats,
+    /// Serve the conversion engine over JSON-RPC on stdio or a unix socket
+    Serve(ServeArgs),
+    /// Write the lessons as one phrase per row for a spreadsheet
+    Export(ExportArgs),
+    /// Turn a CSV or TSV file of phrases into lessons json
+    Import(ImportArgs),
+    /// Write the lessons as a tab separated file Anki imports as notes
+    Anki(AnkiArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PracticeArgs {
+    /// Book to practice from, by title or file name of a deck [default: the lessons file]
+    #[arg(long)]
+    pub book: Option<String>,
+
+    /// Lesson number to practice, may be repeated or comma separated
+    #[arg(long, value_delimiter = ',')]
+    pub lesson: Vec<usize>,
+
+    /// Section to practice, searched in the chosen lessons or in every lesson
+    #[arg(long)]
+    pub section: Vec<String>,
+
+    /// to-jp, to-en, alternate, random or random=<percent EN → JP>
+    #[arg(long)]
+    pub direction: Option<DirectionMode>,
+
+    /// translate, reading (kanji words), kanji-reading (single kanji),
+    /// writing (kanji of a reading) or kana
+    #[arg(long)]
+    pub drill: Option<Drill>,
+
+    /// Kana to drill: hiragana, katakana, dakuten or yoon, comma separated [implies --drill kana]
+    #[arg(long, value_delimiter = ',')]
+    pub kana: Vec<KanaPool>,
+
+    /// Number of questions before the session ends
+    #[arg(long)]
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Script of the output, auto writes uppercase romaji as katakana
+    #[arg(long, value_enum, default_value_t = Script::Auto)]
+    pub script: Script,
+
+    /// Also guess kanji for the kana
+    #[arg(long)]
+    pub kanji: bool,
+
+    /// Output format, json writes one object per line
+    #[arg(long, value_enum, default_value_t = Format::Plain)]
+    pub format: Format,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Listen on a unix socket instead of stdin and stdout
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Row format [default: from the output extension, otherwise csv]
+    #[arg(long, value_enum)]
+    pub format: Option<TableFormat>,
+
+    /// File to write instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// CSV or TSV file with lesson, section, en and jp columns
+    pub path: PathBuf,
+
+    /// Row format [default: from the file extension]
+    #[arg(long, value_enum)]
+    pub format: Option<TableFormat>,
+
+    /// File to write the lessons json to instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct AnkiArgs {
+    /// Lesson number to export, may be repeated or comma separated [default: all]
+    #[arg(long, value_delimiter = ',')]
+    pub lesson: Vec<usize>,
+
+    /// Section to export, searched in the chosen lessons or in every lesson
+    #[arg(long)]
+    pub section: Vec<String>,
+
+    /// Anki deck the notes go into
+    #[arg(long, default_value = "Genki")]
+    pub deck: String,
+
+    /// Front of the cards, {en}, {jp}, {kanji}, {romaji}, {lesson} and {section} are filled in
+    #[arg(long, default_value = anki::DEFAULT_FRONT)]
+    pub front: String,
+
+    /// Back of the cards, lines separated by <br> are left out when empty
+    #[arg(long, default_value = anki::DEFAULT_BACK)]
+    pub back: String,
+
+    /// Add how each phrase went so far to the back of its card
+    #[arg(long)]
+    pub history: bool,
+
+    /// File to write instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+}
+
+impl TableFormat {
+    fn delimiter(self) -> Delimiter {
+        match self {
+            TableFormat::Csv => Delimiter::Comma,
+            TableFormat::Tsv => Delimiter::Tab,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum Script {
+    Auto,
+    Hiragana,
+    Katakana,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+pub enum Format {
+    Plain,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct Conversion {
+    romaji: String,
+    kana: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kanji: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segments: Option<Vec<ConversionSegment>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConversionSegment {
+    kana: String,
+    candidates: Vec<String>,
+}
+
+// set up the session asked for on the command line
+pub fn apply_practice(app: &mut App, args: &PracticeArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(direction_mode) = args.direction {
+        app.context.direction_mode = direction_mode;
+    }
+    if let Some(drill) = args.drill {
+        app.context.drill = drill;
+    }
+    if !args.kana.is_empty() {
+        app.context.drill = Drill::Kana;
+        app.context.kana_pools = args.kana.clone();
+    }
+    if args.count.is_some() {
+        app.context.session_len = args.count;
+    }
+    app.context.current_screen = CurrentScreen::LessonSelect;
+    if let Some(name) = &args.book {
+        let shelf_idx = app
+            .library
+            .shelves
+            .iter()
+            .position(|shelf| {
+                shelf.name.eq_ignore_ascii_case(name)
+                    || shelf.progress_prefix.as_deref() == Some(name.as_str())
+            })
+            .ok_or_else(|| format!("no book named '{name}'"))?;
+        app.open_shelf(shelf_idx);
+    }
+    if args.lesson.is_empty() && args.section.is_empty() {
+        return Ok(());
+    }
+
+    let (selected_lessons, selected_sections) =
+        resolve_selection(&app.book, &args.lesson, &args.section)?;
+    app.context.selected_lessons = selected_lessons;
+    app.context.selected_sections = selected_sections;
+    if !app.start_session() {
+        return Err("the chosen lessons and sections have no phrases".into());
+    }
+    Ok(())
+}
+
+type Selection = (BTreeSet<usize>, BTreeSet<(usize, usize)>);
+
+fn resolve_selection(
+    book: &Book,
+    lessons: &[usize],
+    sections: &[String],
+) -> Result<Selection, Box<dyn Error>> {
+    let mut lesson_indices = Vec::<usize>::new();
+    for &lesson in lessons {
+        let lesson_idx = book
+            .lessons
+            .iter()
+            .position(|candidate| candidate.index == lesson)
+            .ok_or_else(|| format!("no lesson {lesson}"))?;
+        lesson_indices.push(lesson_idx);
+    }
+    if sections.is_empty() {
+        return Ok((lesson_indices.into_iter().collect(), BTreeSet::new()));
+    }
+
+    // sections are looked up in every lesson when no lesson is given
+    if lesson_indices.is_empty() {
+        lesson_indices = (0..book.lessons.len()).collect();
+    }
+    let mut selected_sections = BTreeSet::<(usize, usize)>::new();
+    for name in sections {
+        let mut found = false;
+        for &lesson_idx in &lesson_indices {
+            for (section_idx, section) in book[lesson_idx].sections.iter().enumerate() {
+                if section_matches(&section.name, name) {
+                    selected_sections.insert((lesson_idx, section_idx));
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            return Err(format!("no section named '{name}'").into());
+        }
+    }
+    Ok((BTreeSet::new(), selected_sections))
+}
+
+// matches the full section name or the name without its japanese suffix,
+// e.g. "greetings" matches "Greetings (あいさつ)"
+fn section_matches(section_name: &str, name: &str) -> bool {
+    let short_name = section_name
+        .split_once(" (")
+        .map(|(short_name, _)| short_name)
+        .unwrap_or(section_name);
+    section_name.eq_ignore_ascii_case(name) || short_name.trim().eq_ignore_ascii_case(name)
+}
+
+pub fn convert(resources: &Resources, args: &ConvertArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let kana_converter = RomanjiToKanaConverter::from_file(&resources.kana_path())?;
+    let kanji_converter = if args.kanji {
+        Some(HiragaToKanjiConverter::from_file(&resources.kanji_path())?)
+    } else {
+        None
+    };
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let conversion = convert_line(&line?, args.script, &kana_converter, &kanji_converter);
+        match args.format {
+            Format::Plain => writeln!(
+                stdout,
+                "{}",
+                conversion.kanji.as_ref().unwrap_or(&conversion.kana)
+            )?,
+            Format::Json => writeln!(stdout, "{}", serde_json::to_string(&conversion)?)?,
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert_line(
+    romaji: &str,
+    script: Script,
+    kana_converter: &RomanjiToKanaConverter,
+    kanji_converter: &Option<HiragaToKanjiConverter>,
+) -> Conversion {
+    let kana = match script {
+        Script::Auto => kana_converter.convert(&romaji.to_string()),
+        Script::Hiragana => kana_converter.convert(&romaji.to_lowercase()),
+        Script::Katakana => to_katakana(&kana_converter.convert(&romaji.to_lowercase())),
+    };
+    let mut conversion = Conversion {
+        romaji: romaji.to_string(),
+        kana,
+        kanji: None,
+        segments: None,
+    };
+    if let Some(kanji_converter) = kanji_converter {
+        let segments = kanji_converter.segment(&conversion.kana);
+        conversion.kanji = Some(best_guess(&segments));
+        conversion.segments = Some(
+            segments
+                .into_iter()
+                .map(|segment| ConversionSegment {
+                    kana: segment.kana,
+                    candidates: segment
+                        .candidates
+                        .iter()
+                        .map(|kanji| kanji.to_string())
+                        .collect(),
+                })
+                .collect(),
+        );
+    }
+    conversion
+}
+
+pub fn validate(resources: &Resources) -> Result<ExitCode, Box<dyn Error>> {
+    let mut errors = 0;
+    let mut warnings = 0;
+    if let Err(err) = RomanjiToKanaConverter::from_file(&resources.kana_path()) {
+        println!("error: {err}");
+        errors += 1;
+    }
+    if let Err(err) = HiragaToKanjiConverter::from_file(&resources.kanji_path()) {
+        println!("error: {err}");
+        errors += 1;
+    }
+    match Book::from_file(&resources.lessons_path()) {
+        Ok(book) => {
+            for issue in book.validate() {
+                match issue {
+                    Issue::Error(message) => {
+                        println!("error: {message}");
+                        errors += 1;
+                    }
+                    Issue::Warning(message) => {
+                        println!("warning: {message}");
+                        warnings += 1;
+                    }
+                }
+            }
+        }
+        Err(err) => {
+            println!("error: {err}");
+            errors += 1;
+        }
+    }
+
+    println!("{errors} errors, {warnings} warnings");
+    if errors > 0 {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+pub fn stats(resources: &Resources) -> Result<ExitCode, Box<dyn Error>> {
+    let book = Book::from_file(&resources.lessons_path())?;
+    let mut total_sections = 0;
+    let mut total_phrases = 0;
+    let mut total_kanji = 0;
+    for lesson in &book.lessons {
+        let phrases = lesson
+            .sections
+            .iter()
+            .map(|section| section.phrases.len())
+            .sum::<usize>();
+        let kanji = lesson
+            .sections
+            .iter()
+            .flat_map(|section| &section.phrases)
+            .filter(|phrase| phrase.kanji.is_some())
+            .count();
+        println!(
+            "[{}] {} - {}: {} sections, {} phrases, {} with kanji",
+            lesson.index,
+            lesson.name_en,
+            lesson.name_jp,
+            lesson.sections.len(),
+            phrases,
+            kanji
+        );
+        total_sections += lesson.sections.len();
+        total_phrases += phrases;
+        total_kanji += kanji;
+    }
+    println!(
+        "total: {} lessons, {} sections, {} phrases, {} with kanji",
+        book.lessons.len(),
+        total_sections,
+        total_phrases,
+        total_kanji
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn export(resources: &Resources, args: &ExportArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let book = Book::from_file(&resources.lessons_path())?;
+    let delimiter = args
+        .format
+        .map(TableFormat::delimiter)
+        .or_else(|| args.output.as_deref().and_then(Delimiter::from_path))
+        .unwrap_or(Delimiter::Comma);
+    match &args.output {
+        Some(path) => tabular::export(&book, File::create(path)?, delimiter)?,
+        None => tabular::export(&book, io::stdout().lock(), delimiter)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn import(args: &ImportArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let book = read_table(&args.path, args.format)?;
+    // the same checks as validate, a broken file is not written
+    let mut errors = 0;
+    for issue in book.validate() {
+        match issue {
+            Issue::Error(message) => {
+                eprintln!("error: {message}");
+                errors += 1;
+            }
+            Issue::Warning(message) => eprintln!("warning: {message}"),
+        }
+    }
+    if errors > 0 {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let json = book.to_json()?;
+    match &args.output {
+        Some(path) => fs::write(path, json + "\n")?,
+        None => println!("{json}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn anki(resources: &Resources, args: &AnkiArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let lessons_path = resources.lessons_path();
+    let shelf = Shelf::new(Book::from_file(&lessons_path)?, &lessons_path, None);
+    let book = &shelf.book;
+    let kana_converter = RomanjiToKanaConverter::from_file(&resources.kana_path())?;
+    let sections = export_sections(book, &args.lesson, &args.section)?;
+    let deck = Deck {
+        name: args.deck.clone(),
+        front: args.front.clone(),
+        back: args.back.clone(),
+    };
+    let progress = args.history.then(|| {
+        let mut progress = Progress::load();
+        shelf.migrate_progress(&mut progress);
+        progress
+    });
+    match &args.output {
+        Some(path) => anki::export(
+            book,
+            &sections,
+            &deck,
+            &kana_converter,
+            progress.as_ref(),
+            File::create(path)?,
+        )?,
+        None => anki::export(
+            book,
+            &sections,
+            &deck,
+            &kana_converter,
+            progress.as_ref(),
+            io::stdout().lock(),
+        )?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// every section of the chosen lessons, or of the whole book
+fn export_sections(
+    book: &Book,
+    lessons: &[usize],
+    sections: &[String],
+) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    let (mut selected_lessons, selected_sections) = resolve_selection(book, lessons, sections)?;
+    if !selected_sections.is_empty() {
+        return Ok(selected_sections.into_iter().collect());
+    }
+    if selected_lessons.is_empty() {
+        selected_lessons = (0..book.lessons.len()).collect();
+    }
+    Ok(selected_lessons
+        .into_iter()
+        .flat_map(|lesson_idx| {
+            (0..book[lesson_idx].sections.len()).map(move |section_idx| (lesson_idx, section_idx))
+        })
+        .collect())
+}
+
+fn read_table(path: &Path, format: Option<TableFormat>) -> Result<Book, Box<dyn Error>> {
+    let delimiter = format
+        .map(TableFormat::delimiter)
+        .or_else(|| Delimiter::from_path(path))
+        .ok_or_else(|| format!("cant tell the format of {}, use --format", path.display()))?;
+    let file = File::open(path).map_err(|err| format!("couldnt read {}: {err}", path.display()))?;
+    tabular::import(file, delimiter)
+        .map_err(|err| format!("couldnt parse {}: {err}", path.display()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_selection() {
+        let book = Book::from_file(&Resources::default().lessons_path()).unwrap();
+
+        let (lessons, sections) = resolve_selection(&book, &[1, 3], &[]).unwrap();
+        assert_eq!(lessons, BTreeSet::from([1, 3]));
+        assert!(sections.is_empty());
+
+        // every Time section of lessons 1, 3 and 4
+        let (lessons, sections) =
+            resolve_selection(&book, &[1, 3, 4], &[String::from("time")]).unwrap();
+        assert!(lessons.is_empty());
+        assert_eq!(sections, BTreeSet::from([(1, 2), (3, 3), (4, 3)]));
+
+        let (_, sections) = resolve_selection(&book, &[], &[String::from("Greetings")]).unwrap();
+        assert_eq!(sections, BTreeSet::from([(0, 0)]));
+
+        assert!(resolve_selection(&book, &[42], &[]).is_err());
+        assert!(resolve_selection(&book, &[0], &[String::from("Time")]).is_err());
+    }
+
+    #[test]
+    fn test_export_sections() {
+        let book = Book::from_file(&Resources::default().lessons_path()).unwrap();
+        let sections = export_sections(&book, &[], &[]).unwrap();
+        assert_eq!(
+            sections.len(),
+            book.lessons
+                .iter()
+                .map(|lesson| lesson.sections.len())
+                .sum::<usize>()
+        );
+        assert_eq!(sections[0], (0, 0));
+
+        let sections = export_sections(&book, &[1], &[]).unwrap();
+        assert!(sections.iter().all(|&(lesson_idx, _)| lesson_idx == 1));
+        assert_eq!(sections.len(), book[1].sections.len());
+
+        let sections = export_sections(&book, &[1, 3], &[String::from("time")]).unwrap();
+        assert_eq!(sections, vec![(1, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let cli = Cli::try_parse_from([
+            "keyboard",
+            "practice",
+            "--lesson",
+            "3",
+            "--section",
+            "U-verbs",
+            "--direction",
+            "to-jp",
+            "--count",
+            "20",
+            "--drill",
+            "kanji-reading",
+        ])
+        .unwrap();
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected practice command");
+        };
+        assert_eq!(args.lesson, vec![3]);
+        assert_eq!(args.section, vec![String::from("U-verbs")]);
+        assert_eq!(args.direction, Some(DirectionMode::ToJP));
+        assert_eq!(args.count, Some(20));
+        assert_eq!(args.drill, Some(Drill::KanjiReading));
+
+        let cli = Cli::try_parse_from(["keyboard", "--seed", "5", "practice", "--lesson", "0,1"])
+            .unwrap();
+        assert_eq!(cli.seed, Some(5));
+        assert!(
+            Cli::try_parse_from(["keyboard", "practice", "--direction", "random=101"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["keyboard", "practice", "--drill", "spelling"]).is_err());
+
+        let cli = Cli::try_parse_from(["keyboard", "practice", "--kana", "katakana,yōon"]).unwrap();
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected practice command");
+        };
+        assert_eq!(args.kana, vec![KanaPool::Katakana, KanaPool::Yoon]);
+        assert!(Cli::try_parse_from(["keyboard", "practice", "--kana", "kanji"]).is_err());
+    }
+
+    #[test]
+    fn test_convert_line() {
+        let resources = Resources::default();
+        let kana_converter = RomanjiToKanaConverter::from_file(&resources.kana_path()).unwrap();
+        let kanji_converter = HiragaToKanjiConverter::from_file(&resources.kanji_path()).ok();
+        let test_cases = vec![
+            ("nihon", Script::Auto, "にほん"),
+            ("NIHON", Script::Auto, "ニホン"),
+            ("NIHON", Script::Hiragana, "にほん"),
+            ("nihon", Script::Katakana, "ニホン"),
+            ("ka-do", Script::Katakana, "カード"),
+        ];
+
+        for (romaji, script, expected) in test_cases {
+            let conversion = convert_line(romaji, script, &kana_converter, &None);
+            assert_eq!(conversion.kana, expected, "Failed for romaji: {}", romaji);
+            assert!(conversion.kanji.is_none() && conversion.segments.is_none());
+        }
+
+        let conversion = convert_line("nihon", Script::Auto, &kana_converter, &kanji_converter);
+        assert_eq!(conversion.kanji, Some(String::from("に本")));
+        assert_eq!(
+            serde_json::to_string(&conversion).unwrap(),
+            r#"{"romaji":"nihon","kana":"にほん","kanji":"に本","segments":[{"kana":"に","candidates":["二","日"]},{"kana":"ほん","candidates":["本"]}]}"#
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use crate::kana_converter::RomanjiToKanaConverter;
+use crate::kanji_converter::HiragaToKanjiConverter;
+use crate::resources::Resources;
+
+use std::error::Error;
+
+// romanji input being turned into kana and then kanji, shared by the
+// review screen and the json-rpc server
+pub struct Composer {
+    kana_converter: RomanjiToKanaConverter,
+    kanji_converter: HiragaToKanjiConverter,
+
+    pub romanji: String,
+    pub kana: String,
+    pub kanji: String,
+
+    pub highlighted_kanji: Vec<char>,
+    pub kana_offset: usize,
+    pub kana_len: usize,
+    pub kanji_offset: usize,
+    // (kana offset, kana len, candidate index) of converted spans
+    kanji_offsets: Vec<(usize, usize, usize)>,
+}
+
+impl Composer {
+    pub fn new(resources: &Resources) -> Result<Self, Box<dyn Error>> {
+        Ok(Composer {
+            kana_converter: RomanjiToKanaConverter::from_file(&resources.kana_path())?,
+            kanji_converter: HiragaToKanjiConverter::from_file(&resources.kanji_path())?,
+            romanji: String::new(),
+            kana: String::new(),
+            kanji: String::new(),
+            highlighted_kanji: Vec::new(),
+            kana_offset: 0,
+            kana_len: 1,
+            kanji_offset: 0,
+            kanji_offsets: Vec::new(),
+        })
+    }
+
+    pub fn conversions(&self) -> &[(usize, usize, usize)] {
+        &self.kanji_offsets
+    }
+
+    pub fn input(&mut self, value: char) {
+        self.push_char(value);
+        // when last charachter dissapears do to kana conversion
+        let kana_count = self.kana.chars().count();
+        if self.kana_offset + self.kana_len > kana_count {
+            self.kana_offset = self
+                .kana_offset
+                .saturating_sub(self.kana_offset + self.kana_len - kana_count);
+        }
+        self.update_kanji();
+    }
+
+    pub fn delete_char(&mut self) {
+        self.pop_char();
+        let kana_count = self.kana.chars().count();
+        // undo highlighted all chars if removing highlighted char
+        if kana_count > 0 && self.kana_len > 1 && self.kana_offset + self.kana_len > kana_count {
+            self.kana_len -= 1;
+        }
+        // move cursor back if cursor is at end of string
+        if kana_count > 0 && self.kana_offset + self.kana_len > kana_count {
+            self.kana_offset -= self.kana_offset + self.kana_len - kana_count;
+        }
+        self.update_kanji();
+    }
+
+    pub fn delete_word(&mut self) {
+        // trailing spaces go with the word before them
+        while self.romanji.ends_with(' ') {
+            self.delete_char();
+        }
+        while !self.romanji.is_empty() && !self.romanji.ends_with(' ') {
+            self.delete_char();
+        }
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        if self.kana_offset > 0 {
+            self.kana_offset -= 1;
+        }
+        self.kana_len = 1;
+        self.kanji_offset = 0;
+        self.update_kanji();
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        if self.kana_offset + self.kana_len < self.kana.chars().count() {
+            self.kana_offset += 1;
+        }
+        self.kana_len = 1;
+        self.kanji_offset = 0;
+        self.update_kanji();
+    }
+
+    pub fn extend_selection(&mut self) {
+        if self.kana_offset + self.kana_len < self.kana.chars().count() {
+            self.kana_len += 1;
+        }
+        self.kanji_offset = 0;
+        self.update_kanji();
+    }
+
+    pub fn shrink_selection(&mut self) {
+        if self.kana_len > 1 {
+            self.kana_len -= 1;
+        }
+        self.kanji_offset = 0;
+        self.update_kanji();
+    }
+
+    pub fn previous_candidate(&mut self) {
+        if self.kanji_offset > 0 {
+            self.kanji_offset -= 1;
+        }
+    }
+
+    pub fn next_candidate(&mut self) {
+        if self.kanji_offset + 1 < self.highlighted_kanji.len() {
+            self.kanji_offset += 1;
+        }
+    }
+
+    // converts the highlighted span, converting it again undoes it
+    pub fn convert_span(&mut self) {
+        let kana_count = self.kana.chars().count();
+        if kana_count > 0 {
+            assert!(
+                self.kana_offset < kana_count && self.kana_offset + self.kana_len <= kana_count
+            );
+            self.push_kanji_offset((self.kana_offset, self.kana_len, self.kanji_offset));
+            self.update_kanji();
+        }
+    }
+
+    // kanji candidates for the kana span, None if the span is out of range
+    pub fn candidates(&self, offset: usize, len: usize) -> Option<Vec<char>> {
+        let kana_count = self.kana.chars().count();
+        if len == 0 || offset + len > kana_count {
+            return None;
+        }
+        let kana_substr: String = self.kana.chars().skip(offset).take(len).collect();
+        Some(self.kanji_converter.convert(&kana_substr))
+    }
+
+    // converts the kana span to its candidate, replacing colliding spans
+    pub fn select(&mut self, offset: usize, len: usize, candidate: usize) -> Result<(), String> {
+        let candidates = self
+            .candidates(offset, len)
+            .ok_or_else(|| format!("span {offset}+{len} is outside the kana"))?;
+        if candidate >= candidates.len() {
+            return Err(format!(
+                "span {offset}+{len} has {} candidates",
+                candidates.len()
+            ));
+        }
+        self.kanji_offsets
+            .retain(|&(start, span_len, _)| !(offset < start + span_len && start < offset + len));
+        self.kanji_offsets.push((offset, len, candidate));
+        self.kana_offset = offset;
+        self.kana_len = len;
+        self.kanji_offset = candidate;
+        self.update_kanji();
+        Ok(())
+    }
+
+    // returns the composed text and starts over
+    pub fn commit(&mut self) -> String {
+        let text = self.kanji.clone();
+        self.clear();
+        text
+    }
+
+    pub fn clear(&mut self) {
+        self.romanji.clear();
+        self.kana.clear();
+        self.kanji.clear();
+        self.highlighted_kanji.clear();
+        self.kana_offset = 0;
+        self.kana_len = 1;
+        self.kanji_offset = 0;
+        self.kanji_offsets.clear();
+    }
+
+    pub fn push_char(&mut self, value: char) {
+        self.romanji.push(value);
+        self.kana = self.kana_converter.convert(&self.romanji);
+    }
+
+    pub fn pop_char(&mut self) {
+        self.romanji.pop();
+        self.kana = self.kana_converter.convert(&self.romanji);
+    }
+
+    pub fn push_kanji_offset(&mut self, offset: (usize, usize, usize)) {
+        let start = offset.0;
+        let end = offset.0 + offset.1;
+        let kanji_list_offset = offset.2;
+        assert!(start < self.kana.chars().count() && end <= self.kana.chars().count());
+        let kana_substr: String = self.kana.chars().take(end).skip(start).collect();
+        let kanji_list = self.kanji_converter.convert(&kana_substr);
+        if kanji_list_offset < kanji_list.len() {
+            // if exact match, undo matching
+            if let Some(index) = self
+                .kanji_offsets
+                .iter()
+                .position(|&kanji_offset| kanji_offset == offset)
+            {
+                self.kanji_offsets.remove(index);
+            } else {
+                // remove colliding offsets
+                self.kanji_offsets.retain(|&kanji_offset| {
+                    let start = offset.0;
+                    let end = offset.0 + offset.1;
+                    let start_collides =
+                        start >= kanji_offset.0 && start < kanji_offset.0 + kanji_offset.1;
+                    let end_collides =
+                        end > kanji_offset.0 && end <= kanji_offset.0 + kanji_offset.1;
+                    !start_collides && !end_collides
+                });
+                self.kanji_offsets.push(offset);
+            }
+            self.highlighted_kanji = kanji_list;
+        }
+    }
+
+    pub fn update_kanji(&mut self) {
+        self.kanji = self.kana.clone();
+        // remove offsets that no longer exist because of a backspace
+        self.kanji_offsets.retain(|kanji_offset| {
+            let start = kanji_offset.0;
+            let end = kanji_offset.0 + kanji_offset.1;
+            start <= self.kana.chars().count() && end <= self.kana.chars().count()
+        });
+        // generate kanji from kana and offsets
+        if self.kanji.chars().count() > 0 {
+            // sort to keep adjusted offset valid
+            self.kanji_offsets.sort();
+            let mut offset_adjust: usize = 0;
+            // update kanji text
+            for kanji_offset in &self.kanji_offsets {
+                assert!(kanji_offset.1 >= 1);
+                let start = kanji_offset.0;
+                let end = kanji_offset.0 + kanji_offset.1;
+                let kanji_list_offset = kanji_offset.2;
+                assert!(start <= self.kana.chars().count() && end <= self.kana.chars().count());
+                let kana_substr: String = self.kana.chars().take(end).skip(start).collect();
+                let kanji_list = self.kanji_converter.convert(&kana_substr);
+                if kanji_list_offset < kanji_list.len() {
+                    let kanji_char = String::from(kanji_list[kanji_list_offset]);
+                    let start_byte_index = self
+                        .kanji
+                        .char_indices()
+                        .nth(start - offset_adjust)
+                        .map(|(i, _)| i)
+                        .unwrap_or(self.kanji.len());
+                    let end_byte_index = self
+                        .kanji
+                        .char_indices()
+                        .nth(end - offset_adjust)
+                        .map(|(i, _)| i)
+                        .unwrap_or(self.kanji.len());
+                    self.kanji
+                        .replace_range(start_byte_index..end_byte_index, &kanji_char);
+                    // adjust for shorter len kanji than hiragana after swap
+                    offset_adjust += kanji_offset.1 - 1;
+                }
+            }
+
+            assert!(
+                self.kana_offset < self.kana.chars().count()
+                    && self.kana_offset + self.kana_len <= self.kana.chars().count()
+            );
+            let kana_substr: String = self
+                .kana
+                .chars()
+                .take(self.kana_offset + self.kana_len)
+                .skip(self.kana_offset)
+                .collect();
+            self.highlighted_kanji = self.kanji_converter.convert(&kana_substr);
+        } else {
+            self.highlighted_kanji.clear();
+        }
+    }
+
+    pub fn kana_converter(&self) -> &RomanjiToKanaConverter {
+        &self.kana_converter
+    }
+
+    pub fn kanji_converter(&self) -> &HiragaToKanjiConverter {
+        &self.kanji_converter
+    }
+
+    pub fn get_romanji(&self) -> String {
+        self.romanji.clone()
+    }
+
+    pub fn get_kana(&self) -> String {
+        self.kana.clone()
+    }
+
+    pub fn get_kanji(&self) -> String {
+        self.kanji.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_romanji(composer: &mut Composer, romanji: &str) {
+        for value in romanji.chars() {
+            composer.input(value);
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let mut composer = Composer::new(&Resources::default()).unwrap();
+        type_romanji(&mut composer, "nihongo");
+        assert_eq!(composer.kana, "にほんご");
+        assert_eq!(composer.candidates(1, 2), Some(vec!['本']));
+        assert_eq!(composer.candidates(3, 2), None);
+        assert_eq!(composer.candidates(0, 0), None);
+
+        composer.select(0, 1, 1).unwrap();
+        composer.select(1, 2, 0).unwrap();
+        assert_eq!(composer.kanji, "日本ご");
+        assert_eq!(composer.conversions(), &[(0, 1, 1), (1, 2, 0)]);
+
+        // selecting a span again replaces the spans it collides with
+        composer.select(0, 1, 0).unwrap();
+        assert_eq!(composer.conversions(), &[(0, 1, 0), (1, 2, 0)]);
+        assert!(composer.select(1, 1, 0).is_err());
+        assert!(composer.select(0, 1, 9).is_err());
+        assert!(composer.select(4, 1, 0).is_err());
+
+        assert_eq!(composer.commit(), "二本ご");
+        assert!(composer.romanji.is_empty() && composer.conversions().is_empty());
+        assert_eq!((composer.kana_offset, composer.kana_len), (0, 1));
+    }
+}
@@ -0,0 +1,314 @@
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app::{App, CurrentScreen, DirectionMode};
+use crate::keymap::{self, Key, Keymap};
+use crate::resources::Resources;
+use crate::theme::{Colors, ThemeName};
+
+// config.toml as written, checked by Config::parse
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct RawConfig {
+    direction: Option<String>,
+    session_len: Option<usize>,
+    theme: Option<String>,
+    resources: RawResources,
+    colors: RawColors,
+    keys: BTreeMap<String, BTreeMap<String, KeyList>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawResources {
+    dir: Option<PathBuf>,
+    lessons: Option<PathBuf>,
+    kana: Option<PathBuf>,
+    kanji: Option<PathBuf>,
+    decks: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawColors {
+    accent: Option<String>,
+    highlight: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    // overrides the direction remembered in the preferences
+    pub direction_mode: Option<DirectionMode>,
+    pub session_len: Option<usize>,
+    // overrides the theme remembered in the preferences
+    pub theme: Option<ThemeName>,
+    resources: RawResources,
+    pub colors: Colors,
+    pub keymap: Keymap,
+}
+
+impl Config {
+    // a missing config file is fine unless it was asked for by path
+    pub fn load(path: Option<&Path>) -> Result<Self, Vec<String>> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Config::path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let toml = match fs::read_to_string(&path) {
+            Ok(toml) => toml,
+            Err(_) if !required && !path.exists() => return Ok(Config::default()),
+            Err(err) => return Err(vec![format!("couldnt read {}: {err}", path.display())]),
+        };
+        Config::parse(&toml).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| format!("{}: {error}", path.display()))
+                .collect()
+        })
+    }
+
+    // every problem in the file is reported, not just the first
+    pub fn parse(toml: &str) -> Result<Self, Vec<String>> {
+        let raw: RawConfig = toml::from_str(toml).map_err(|err| vec![err.to_string()])?;
+        let mut errors = Vec::<String>::new();
+        let mut config = Config::default();
+
+        if let Some(direction) = &raw.direction {
+            match direction.parse::<DirectionMode>() {
+                Ok(direction_mode) => config.direction_mode = Some(direction_mode),
+                Err(err) => errors.push(format!("direction: {err}")),
+            }
+        }
+        match raw.session_len {
+            Some(0) => errors.push(String::from("session-len: must be at least 1")),
+            session_len => config.session_len = session_len,
+        }
+        if let Some(theme) = &raw.theme {
+            match theme.parse::<ThemeName>() {
+                Ok(theme) => config.theme = Some(theme),
+                Err(err) => errors.push(format!("theme: {err}")),
+            }
+        }
+        if let Some(accent) = &raw.colors.accent {
+            match parse_color(accent) {
+                Ok(color) => config.colors.accent = Some(color),
+                Err(err) => errors.push(format!("colors.accent: {err}")),
+            }
+        }
+        if let Some(highlight) = &raw.colors.highlight {
+            match parse_color(highlight) {
+                Ok(color) => config.colors.highlight = Some(color),
+                Err(err) => errors.push(format!("colors.highlight: {err}")),
+            }
+        }
+        for (screen_name, bindings) in &raw.keys {
+            let Some(screen) = screen_from_name(screen_name) else {
+                errors.push(format!("keys.{screen_name}: unknown screen"));
+                continue;
+            };
+            for (action_name, key_list) in bindings {
+                let Some(action) = keymap::action_from_name(action_name) else {
+                    errors.push(format!("keys.{screen_name}.{action_name}: unknown action"));
+                    continue;
+                };
+                let names = match key_list {
+                    KeyList::One(name) => std::slice::from_ref(name),
+                    KeyList::Many(names) => names.as_slice(),
+                };
+                let mut keys = Vec::<Key>::new();
+                for name in names {
+                    match name.parse::<Key>() {
+                        Ok(key) => keys.push(key),
+                        Err(err) => errors.push(format!("keys.{screen_name}.{action_name}: {err}")),
+                    }
+                }
+                config.keymap.bind(screen, action, &keys);
+            }
+        }
+        config.resources = raw.resources;
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // the --resources flag beats the config file
+    pub fn resources(&self, dir: Option<PathBuf>) -> Resources {
+        let dir = dir
+            .or_else(|| self.resources.dir.clone())
+            .unwrap_or_else(|| PathBuf::from("resources"));
+        Resources {
+            dir,
+            lessons: self.resources.lessons.clone(),
+            kana: self.resources.kana.clone(),
+            kanji: self.resources.kanji.clone(),
+            decks: self.resources.decks.clone(),
+        }
+    }
+
+    pub fn apply(&self, app: &mut App) {
+        if let Some(direction_mode) = self.direction_mode {
+            app.context.direction_mode = direction_mode;
+        }
+        app.context.session_len = self.session_len;
+        app.colors = self.colors;
+        app.set_theme(self.theme.unwrap_or(app.theme.name));
+        app.keymap = self.keymap.clone();
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("genki-keyboard").join("config.toml"))
+    }
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    value
+        .parse::<Color>()
+        .map_err(|_| format!("unknown colour '{value}'"))
+}
+
+fn screen_from_name(name: &str) -> Option<CurrentScreen> {
+    match name {
+        "welcome" => Some(CurrentScreen::Welcome),
+        "lesson-select" => Some(CurrentScreen::LessonSelect),
+        "review" => Some(CurrentScreen::Review),
+        "browse" => Some(CurrentScreen::Browse),
+        "search" => Some(CurrentScreen::Search),
+        "book-select" => Some(CurrentScreen::BookSelect),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Action;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r##"
+            direction = "random=30"
+            session-len = 20
+            theme = "high-contrast"
+
+            [resources]
+            dir = "decks"
+            lessons = "genki.json"
+            decks = "/home/me/decks"
+
+            [colors]
+            accent = "cyan"
+            highlight = "#ffffff"
+
+            [keys.review]
+            convert = "space"
+            delete-word = ["ctrl+w", "alt+backspace"]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(
+            config.direction_mode,
+            Some(DirectionMode::Random { to_jp_percent: 30 })
+        );
+        assert_eq!(config.session_len, Some(20));
+        assert_eq!(config.theme, Some(ThemeName::HighContrast));
+        assert_eq!(config.colors.accent, Some(Color::Cyan));
+        assert_eq!(config.colors.highlight, Some(Color::Rgb(255, 255, 255)));
+
+        let resources = config.resources(None);
+        assert_eq!(resources.lessons_path(), PathBuf::from("decks/genki.json"));
+        assert_eq!(resources.kana_path(), PathBuf::from("decks/kana.json"));
+        assert_eq!(resources.decks_path(), PathBuf::from("/home/me/decks"));
+        let resources = config.resources(Some(PathBuf::from("other")));
+        assert_eq!(resources.lessons_path(), PathBuf::from("other/genki.json"));
+
+        let test_cases = vec![
+            (
+                KeyCode::Char(' '),
+                KeyModifiers::NONE,
+                Some(Action::ConvertSpan),
+            ),
+            (KeyCode::Tab, KeyModifiers::NONE, None),
+            (
+                KeyCode::Char('w'),
+                KeyModifiers::CONTROL,
+                Some(Action::DeleteWord),
+            ),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::ALT,
+                Some(Action::DeleteWord),
+            ),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::NONE,
+                Some(Action::DeleteChar),
+            ),
+            // no binding for ctrl+backspace, plain backspace doesn't take it
+            (KeyCode::Backspace, KeyModifiers::CONTROL, None),
+        ];
+        for (code, modifiers, expected) in test_cases {
+            let result = config
+                .keymap
+                .action_for(CurrentScreen::Review, KeyEvent::new(code, modifiers));
+            assert_eq!(
+                result, expected,
+                "Failed for key: {:?} {:?}",
+                code, modifiers
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let test_cases = vec![
+            ("direction = \"sideways\"", "direction: "),
+            ("session-len = 0", "session-len: must be at least 1"),
+            ("theme = \"neon\"", "theme: unknown theme 'neon'"),
+            (
+                "[colors]\naccent = \"sparkly\"",
+                "colors.accent: unknown colour 'sparkly'",
+            ),
+            ("[keys.shop]\nback = \"esc\"", "keys.shop: unknown screen"),
+            (
+                "[keys.review]\nfly = \"f\"",
+                "keys.review.fly: unknown action",
+            ),
+            (
+                "[keys.review]\nconvert = \"hyper+tab\"",
+                "keys.review.convert: unknown modifier 'hyper' in 'hyper+tab'",
+            ),
+            (
+                "[keys.review]\nconvert = \"tabby\"",
+                "keys.review.convert: unknown key 'tabby'",
+            ),
+            ("colour = \"red\"", "unknown field `colour`"),
+        ];
+        for (toml, expected) in test_cases {
+            let errors = Config::parse(toml).unwrap_err();
+            assert_eq!(errors.len(), 1, "Failed for toml: {}", toml);
+            assert!(errors[0].contains(expected), "Failed for toml: {}", toml);
+        }
+
+        // all problems are reported together
+        let errors = Config::parse("session-len = 0\n[colors]\nhighlight = \"nope\"").unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::kana_converter::to_hiragana;
+use crate::kanji_converter::HiragaToKanjiConverter;
+
+// part of a kanji spelling, kana parts have no reading above them
+#[derive(Debug, PartialEq)]
+pub struct Ruby {
+    pub base: String,
+    pub reading: Option<String>,
+}
+
+// lines up the kanji spelling of a phrase with its kana
+pub struct Furigana {
+    readings: HashMap<char, Vec<String>>,
+}
+
+impl Furigana {
+    pub fn new(kanji_converter: &HiragaToKanjiConverter) -> Self {
+        let mut readings = kanji_converter.readings();
+        // longer readings first so 日本 is read にほん before に + ほ
+        for kanji_readings in readings.values_mut() {
+            kanji_readings.sort_by_key(|reading| std::cmp::Reverse(reading.chars().count()));
+        }
+        Furigana { readings }
+    }
+
+    // readings of a kanji in the dictionary, longest first, without the
+    // shortened ones like いっ that only appear inside words
+    pub fn readings(&self, kanji: char) -> Vec<&str> {
+        self.readings
+            .get(&kanji)
+            .into_iter()
+            .flatten()
+            .filter(|reading| !reading.ends_with('っ'))
+            .map(String::as_str)
+            .collect()
+    }
+
+    // every kanji gets its dictionary reading, runs of kanji the dictionary
+    // doesnt know are read together. None when the kana dont fit the kanji
+    pub fn align(&self, kanji: &str, jp: &str) -> Option<Vec<Ruby>> {
+        // spaces only split words in the kana
+        let base: Vec<char> = kanji
+            .chars()
+            .filter(|value| !value.is_whitespace())
+            .collect();
+        let kana: Vec<char> = jp.chars().filter(|value| !value.is_whitespace()).collect();
+        let mut rubies = Vec::<Ruby>::new();
+        if !base.iter().any(|&value| is_kanji(value)) || !self.align_from(&base, &kana, &mut rubies)
+        {
+            return None;
+        }
+        Some(rubies)
+    }
+
+    fn align_from(&self, base: &[char], kana: &[char], rubies: &mut Vec<Ruby>) -> bool {
+        let Some(&first) = base.first() else {
+            return kana.is_empty();
+        };
+        if !is_kanji(first) {
+            if kana.first().is_none_or(|&value| !same_kana(value, first)) {
+                return false;
+            }
+            let len = rubies.len();
+            match rubies.last_mut() {
+                Some(ruby) if ruby.reading.is_none() => ruby.base.push(first),
+                _ => rubies.push(Ruby {
+                    base: first.to_string(),
+                    reading: None,
+                }),
+            }
+            if self.align_from(&base[1..], &kana[1..], rubies) {
+                return true;
+            }
+            undo(rubies, len, first);
+            return false;
+        }
+
+        let len = rubies.len();
+        for reading in self.readings.get(&first).into_iter().flatten() {
+            let reading_len = reading.chars().count();
+            if reading_len <= kana.len() && kana[..reading_len].iter().copied().eq(reading.chars())
+            {
+                rubies.push(Ruby {
+                    base: first.to_string(),
+                    reading: Some(reading.clone()),
+                });
+                if self.align_from(&base[1..], &kana[reading_len..], rubies) {
+                    return true;
+                }
+                rubies.truncate(len);
+            }
+        }
+
+        // a run of kanji read as a whole, the shortest reading that lets
+        // the rest line up wins
+        let run_len = base.iter().take_while(|&&value| is_kanji(value)).count();
+        for reading_len in run_len.min(kana.len()).max(1)..=kana.len() {
+            rubies.push(Ruby {
+                base: base[..run_len].iter().collect(),
+                reading: Some(kana[..reading_len].iter().collect()),
+            });
+            if self.align_from(&base[run_len..], &kana[reading_len..], rubies) {
+                return true;
+            }
+            rubies.truncate(len);
+        }
+        false
+    }
+}
+
+// takes back a kana added by align_from
+fn undo(rubies: &mut Vec<Ruby>, len: usize, value: char) {
+    if rubies.len() > len {
+        rubies.truncate(len);
+    } else if let Some(ruby) = rubies.last_mut() {
+        let popped = ruby.base.pop();
+        debug_assert_eq!(popped, Some(value));
+    }
+}
+
+// katakana in the kanji spelling match hiragana in the kana
+fn same_kana(kana: char, base: char) -> bool {
+    kana == base || to_hiragana(&kana.to_string()) == to_hiragana(&base.to_string())
+}
+
+// 々 repeats the kanji before it and is read like one
+pub fn is_kanji(value: char) -> bool {
+    matches!(value, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn ruby(base: &str, reading: Option<&str>) -> Ruby {
+        Ruby {
+            base: String::from(base),
+            reading: reading.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_align() {
+        let converter =
+            HiragaToKanjiConverter::from_file(Path::new("resources/kanji.json")).unwrap();
+        let furigana = Furigana::new(&converter);
+        let test_cases = vec![
+            (
+                "日本",
+                "にほん",
+                Some(vec![ruby("日", Some("に")), ruby("本", Some("ほん"))]),
+            ),
+            (
+                "三時",
+                "さんじ",
+                Some(vec![ruby("三", Some("さん")), ruby("時", Some("じ"))]),
+            ),
+            (
+                "日本人",
+                "にほんじん",
+                Some(vec![
+                    ruby("日", Some("に")),
+                    ruby("本", Some("ほん")),
+                    ruby("人", Some("じん")),
+                ]),
+            ),
+            // okurigana stay without a reading
+            (
+                "行く",
+                "いく",
+                Some(vec![ruby("行", Some("い")), ruby("く", None)]),
+            ),
+            (
+                "お金",
+                "おかね",
+                Some(vec![ruby("お", None), ruby("金", Some("かね"))]),
+            ),
+            // spaces in the kana and katakana in the kanji spelling
+            (
+                "日本語のクラス",
+                "にほんごの くらす",
+                Some(vec![
+                    ruby("日", Some("に")),
+                    ruby("本", Some("ほん")),
+                    ruby("語", Some("ご")),
+                    ruby("のクラス", None),
+                ]),
+            ),
+            // 々 and kanji missing from the dictionary are read as a run
+            (
+                "時々",
+                "ときどき",
+                Some(vec![ruby("時", Some("とき")), ruby("々", Some("どき"))]),
+            ),
+            (
+                "鬱陶しい",
+                "うっとうしい",
+                Some(vec![ruby("鬱陶", Some("うっとう")), ruby("しい", None)]),
+            ),
+            ("行く", "くる", None),
+            ("いく", "いく", None),
+        ];
+        for (kanji, jp, expected) in test_cases {
+            assert_eq!(
+                furigana.align(kanji, jp),
+                expected,
+                "Failed for kanji: {}",
+                kanji
+            );
+        }
+    }
+
+    #[test]
+    fn test_align_lessons() {
+        // almost every kanji spelling of the shipped lessons lines up
+        let converter =
+            HiragaToKanjiConverter::from_file(Path::new("resources/kanji.json")).unwrap();
+        let furigana = Furigana::new(&converter);
+        let book = crate::lessons::Book::from_file(Path::new("resources/lessons.json")).unwrap();
+        let phrases: Vec<_> = book
+            .lessons
+            .iter()
+            .flat_map(|lesson| &lesson.sections)
+            .flat_map(|section| &section.phrases)
+            .filter(|phrase| phrase.kanji.is_some())
+            .collect();
+        let aligned = phrases
+            .iter()
+            .filter(|phrase| {
+                furigana
+                    .align(phrase.kanji.as_ref().unwrap(), &phrase.jp)
+                    .is_some()
+            })
+            .count();
+        assert!(
+            aligned * 10 >= phrases.len() * 9,
+            "{aligned} of {}",
+            phrases.len()
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use crate::app::TranslationDirection;
+use crate::kana_converter::{RomanjiToKanaConverter, to_hiragana};
+use crate::kana_drill;
+use crate::lessons::Phrase;
+
+// whether an answer to a phrase asked in a direction counts as correct,
+// english is compared loosely and japanese may be kanji or kana
+pub fn is_correct(phrase: &Phrase, direction: TranslationDirection, answer: &str) -> bool {
+    match direction {
+        TranslationDirection::ToEN => {
+            let answer = normalize_english(answer);
+            !answer.is_empty() && english_answers(&phrase.en).contains(&answer)
+        }
+        TranslationDirection::ToJP => {
+            let answer = normalize_japanese(answer);
+            !answer.is_empty()
+                && std::iter::once(&phrase.jp)
+                    .chain(&phrase.kanji)
+                    .any(|expected| normalize_japanese(expected) == answer)
+        }
+    }
+}
+
+// the kana typed for the kanji of a phrase, any of the readings separated
+// by semicolons counts
+pub fn is_correct_reading(phrase: &Phrase, answer: &str) -> bool {
+    let answer = normalize_japanese(answer);
+    !answer.is_empty()
+        && phrase
+            .jp
+            .split(';')
+            .any(|reading| normalize_japanese(reading) == answer)
+}
+
+// the kanji picked for a phrase, only its exact spelling counts
+pub fn is_correct_writing(phrase: &Phrase, answer: &str) -> bool {
+    let answer: String = answer
+        .chars()
+        .filter(|value| !value.is_whitespace())
+        .collect();
+    phrase.kanji.as_ref().is_some_and(|kanji| {
+        !answer.is_empty()
+            && kanji
+                .chars()
+                .filter(|value| !value.is_whitespace())
+                .eq(answer.chars())
+    })
+}
+
+// a kana drill question, any romanji the rules type the kana with or the
+// kana of the romanji in the same script. kana that share their romanji,
+// like ず and づ, count for each other
+pub fn is_correct_kana(
+    phrase: &Phrase,
+    direction: TranslationDirection,
+    answer: &str,
+    converter: &RomanjiToKanaConverter,
+) -> bool {
+    match direction {
+        TranslationDirection::ToEN => {
+            let answer = normalize_english(answer);
+            !answer.is_empty()
+                && (answer == phrase.en || converter.convert(&answer) == to_hiragana(&phrase.jp))
+        }
+        TranslationDirection::ToJP => {
+            let answer: String = answer
+                .chars()
+                .filter(|value| !value.is_whitespace())
+                .collect();
+            !answer.is_empty()
+                && kana_drill::script_label(&answer) == kana_drill::script_label(&phrase.jp)
+                && (answer == phrase.jp || converter.to_romanji(&answer) == phrase.en)
+        }
+    }
+}
+
+// "home; house; my place" accepts each of the three, "to wait (~を)"
+// accepts "to wait" and "wait"
+fn english_answers(en: &str) -> Vec<String> {
+    let en = strip_parentheses(en);
+    let mut answers = vec![normalize_english(&en)];
+    for alternative in en.split([';', '/', ',']) {
+        let alternative = normalize_english(alternative);
+        if let Some(verb) = alternative.strip_prefix("to ") {
+            answers.push(String::from(verb));
+        }
+        answers.push(alternative);
+    }
+    answers.retain(|answer| !answer.is_empty());
+    answers
+}
+
+fn normalize_english(text: &str) -> String {
+    strip_parentheses(text)
+        .to_lowercase()
+        .chars()
+        .map(|value| {
+            if value.is_alphanumeric() || value == '\'' {
+                value
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn normalize_japanese(text: &str) -> String {
+    to_hiragana(&strip_parentheses(text))
+        .chars()
+        .filter(|value| {
+            !value.is_whitespace()
+                && !matches!(value, '~' | '〜' | '.' | '…' | '。' | '、' | '?' | '？')
+        })
+        .collect()
+}
+
+fn strip_parentheses(text: &str) -> String {
+    let mut depth = 0;
+    text.chars()
+        .filter(|&value| match value {
+            '(' | '（' => {
+                depth += 1;
+                false
+            }
+            ')' | '）' => {
+                depth = 0.max(depth - 1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phrase(en: &str, jp: &str, kanji: Option<&str>) -> Phrase {
+        Phrase {
+            en: String::from(en),
+            jp: String::from(jp),
+            kanji: kanji.map(String::from),
+            ..Phrase::default()
+        }
+    }
+
+    #[test]
+    fn test_is_correct() {
+        let test_cases = vec![
+            (
+                phrase("good morning", "おはよう", None),
+                TranslationDirection::ToEN,
+                "Good  Morning!",
+                true,
+            ),
+            (
+                phrase("home; house; my place", "うち", None),
+                TranslationDirection::ToEN,
+                "house",
+                true,
+            ),
+            (
+                phrase("home; house; my place", "うち", None),
+                TranslationDirection::ToEN,
+                "home house",
+                false,
+            ),
+            (
+                phrase("to wait (~を)", "まつ", Some("待つ")),
+                TranslationDirection::ToEN,
+                "wait",
+                true,
+            ),
+            (
+                phrase("Mr/Ms...", "さん", None),
+                TranslationDirection::ToEN,
+                "ms",
+                true,
+            ),
+            (
+                phrase("5 o'clock", "ごじ", Some("五時")),
+                TranslationDirection::ToEN,
+                "5 o'clock",
+                true,
+            ),
+            (
+                phrase("good morning", "おはよう", None),
+                TranslationDirection::ToEN,
+                "",
+                false,
+            ),
+            (
+                phrase("to wait (~を)", "まつ", Some("待つ")),
+                TranslationDirection::ToJP,
+                "待つ",
+                true,
+            ),
+            (
+                phrase("to wait (~を)", "まつ", Some("待つ")),
+                TranslationDirection::ToJP,
+                "まつ",
+                true,
+            ),
+            (
+                phrase("to wait (~を)", "まつ", Some("待つ")),
+                TranslationDirection::ToJP,
+                "まち",
+                false,
+            ),
+            (
+                phrase("coffee", "コーヒー", None),
+                TranslationDirection::ToJP,
+                "こーひー",
+                true,
+            ),
+            (
+                phrase("excuse me", "すみません。", None),
+                TranslationDirection::ToJP,
+                "すみません",
+                true,
+            ),
+            (
+                phrase("excuse me", "すみません", None),
+                TranslationDirection::ToJP,
+                " ",
+                false,
+            ),
+        ];
+
+        for (phrase, direction, answer, expected) in test_cases {
+            assert_eq!(
+                is_correct(&phrase, direction, answer),
+                expected,
+                "Failed for answer: {}",
+                answer
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_correct_kana() {
+        let converter =
+            RomanjiToKanaConverter::from_file(std::path::Path::new("resources/kana.json")).unwrap();
+        let test_cases = vec![
+            ("ka", "か", TranslationDirection::ToEN, "ka", true),
+            ("ka", "か", TranslationDirection::ToEN, " KA ", true),
+            ("ka", "か", TranslationDirection::ToEN, "ga", false),
+            ("shi", "シ", TranslationDirection::ToEN, "shi", true),
+            ("ka", "か", TranslationDirection::ToJP, "か", true),
+            ("ka", "か", TranslationDirection::ToJP, "カ", false),
+            ("ka", "か", TranslationDirection::ToJP, "が", false),
+            ("ka", "カ", TranslationDirection::ToJP, "カ", true),
+            ("ka", "カ", TranslationDirection::ToJP, "か", false),
+            ("zu", "づ", TranslationDirection::ToJP, "ず", true),
+            ("kya", "きゃ", TranslationDirection::ToJP, "きや", false),
+            ("ka", "か", TranslationDirection::ToJP, "", false),
+            ("ka", "か", TranslationDirection::ToEN, "", false),
+        ];
+        for (en, jp, direction, answer, expected) in test_cases {
+            assert_eq!(
+                is_correct_kana(&phrase(en, jp, None), direction, answer, &converter),
+                expected,
+                "Failed for answer: {}",
+                answer
+            );
+        }
+
+        // rules with more than one way to type a kana accept each of them
+        let path =
+            std::env::temp_dir().join(format!("keyboard-grading-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"roots": [
+                {"key": "s", "next": [{"key": "i", "value": "し"}, {"key": "h", "next": [{"key": "i", "value": "し"}]}]},
+                {"key": "o", "value": "を"},
+                {"key": "w", "next": [{"key": "o", "value": "を"}]}
+            ]}"#,
+        )
+        .unwrap();
+        let converter = RomanjiToKanaConverter::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let test_cases = vec![
+            ("shi", "し", "si", true),
+            ("shi", "シ", "SI", true),
+            ("shi", "し", "shi", true),
+            ("wo", "を", "o", true),
+            ("wo", "を", "s", false),
+        ];
+        for (en, jp, answer, expected) in test_cases {
+            assert_eq!(
+                is_correct_kana(
+                    &phrase(en, jp, None),
+                    TranslationDirection::ToEN,
+                    answer,
+                    &converter
+                ),
+                expected,
+                "Failed for answer: {}",
+                answer
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_correct_writing() {
+        let test_cases = vec![
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "待つ", true),
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "まつ", false),
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "松", false),
+            (
+                phrase("Japanese language", "にほんご", Some("日本語")),
+                "日本 語",
+                true,
+            ),
+            (
+                phrase("Japanese language", "にほんご", Some("日本語")),
+                "日本",
+                false,
+            ),
+            (
+                phrase("Japanese language", "にほんご", Some("日本語")),
+                "二本語",
+                false,
+            ),
+            (phrase("yes", "はい", None), "はい", false),
+            (phrase("yes", "はい", None), "", false),
+        ];
+        for (phrase, answer, expected) in test_cases {
+            assert_eq!(
+                is_correct_writing(&phrase, answer),
+                expected,
+                "Failed for answer: {}",
+                answer
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_correct_reading() {
+        let test_cases = vec![
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "まつ", true),
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "マツ", true),
+            (phrase("to wait (~を)", "まつ", Some("待つ")), "待つ", false),
+            (phrase("Japan", "にほん", Some("日本")), "にっぽん", false),
+            (
+                phrase("日本", "に; にち; ひ; か; び", Some("日")),
+                "ひ",
+                true,
+            ),
+            (
+                phrase("日本", "に; にち; ひ; か; び", Some("日")),
+                "にち",
+                true,
+            ),
+            (
+                phrase("日本", "に; にち; ひ; か; び", Some("日")),
+                "",
+                false,
+            ),
+        ];
+        for (phrase, answer, expected) in test_cases {
+            assert_eq!(
+                is_correct_reading(&phrase, answer),
+                expected,
+                "Failed for answer: {}",
+                answer
+            );
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs;
+use std::path::Path;
 
 #[derive(Debug, Deserialize)]
 struct Head {
@@ -34,20 +37,20 @@ impl<'a> Phrase<'a> {
 
     fn compare(&mut self, node: &Kana) -> bool {
         let first = self.romanji.chars().nth(self.offset + self.len);
-        if let Some(first) = &first {
-            if node.key == *first {
-                self.len += 1;
-                if let Some(value) = &node.value {
-                    self.kana.push_str(value);
-                }
-                return true;
+        if let Some(first) = &first
+            && node.key == *first
+        {
+            self.len += 1;
+            if let Some(value) = &node.value {
+                self.kana.push_str(value);
             }
+            return true;
         }
-        return false;
+        false
     }
 
     fn done(&self) -> bool {
-        return self.offset >= self.romanji.chars().count();
+        self.offset >= self.romanji.chars().count()
     }
 
     fn next(&mut self) {
@@ -72,23 +75,39 @@ impl<'a> Phrase<'a> {
     }
 
     fn get_kana(self) -> String {
-        return self.kana;
+        self.kana
     }
 }
 
 pub struct RomanjiToKanaConverter {
     head: Head,
+    // kana back to the romanji typing it, for showing romanji of a phrase
+    romanji: HashMap<String, String>,
+    longest_kana: usize,
 }
 
 impl RomanjiToKanaConverter {
-    pub fn new() -> Self {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
         // read hiragana/katakana rules json
-        let json =
-            fs::read_to_string("resources/kana.json").expect("couldnt read resources/kana.json");
+        let json = fs::read_to_string(path)
+            .map_err(|err| format!("couldnt read {}: {err}", path.display()))?;
 
-        RomanjiToKanaConverter {
-            head: serde_json::from_str::<Head>(&json).unwrap(),
+        let head = serde_json::from_str::<Head>(&json)
+            .map_err(|err| format!("couldnt parse {}: {err}", path.display()))?;
+        let mut romanji = HashMap::<String, String>::new();
+        for root in &head.roots {
+            collect_romanji(root, &mut String::new(), &mut romanji);
         }
+        let longest_kana = romanji
+            .keys()
+            .map(|kana| kana.chars().count())
+            .max()
+            .unwrap_or(0);
+        Ok(RomanjiToKanaConverter {
+            head,
+            romanji,
+            longest_kana,
+        })
     }
 
     fn convert_phrase(&self, phrase: &mut Phrase) -> bool {
@@ -101,11 +120,11 @@ impl RomanjiToKanaConverter {
                 phrase.reset();
             }
         }
-        return false;
+        false
     }
 
     fn iterate_kana(&self, node: &Kana, phrase: &mut Phrase) -> bool {
-        let mut matched = phrase.compare(&node);
+        let mut matched = phrase.compare(node);
         if matched {
             for child in &node.next {
                 matched = self.iterate_kana(child, phrase);
@@ -114,11 +133,11 @@ impl RomanjiToKanaConverter {
                 }
             }
         }
-        return matched;
+        matched
     }
 
     pub fn convert(&self, romanji: &String) -> String {
-        let mut phrase = Phrase::new(&romanji);
+        let mut phrase = Phrase::new(romanji);
         while !phrase.done() {
             if self.convert_phrase(&mut phrase) {
                 phrase.next();
@@ -126,8 +145,88 @@ impl RomanjiToKanaConverter {
                 phrase.skip();
             }
         }
-        return phrase.get_kana();
+        phrase.get_kana()
+    }
+
+    // every kana the rules type, in the order of the rules file
+    pub fn kana(&self) -> Vec<String> {
+        let mut kana = Vec::<String>::new();
+        for root in &self.head.roots {
+            collect_kana(root, &mut kana);
+        }
+        kana
+    }
+
+    // lowercase romanji for kana, longest kana matched first so っか
+    // becomes kka, unknown chars are kept
+    pub fn to_romanji(&self, kana: &str) -> String {
+        let chars: Vec<char> = kana.chars().collect();
+        let mut romanji = String::new();
+        let mut offset = 0;
+        while offset < chars.len() {
+            let longest = self.longest_kana.min(chars.len() - offset);
+            let matched = (1..=longest).rev().find_map(|len| {
+                let kana: String = chars[offset..offset + len].iter().collect();
+                self.romanji.get(&kana).map(|value| (len, value))
+            });
+            match matched {
+                Some((len, value)) => {
+                    romanji.push_str(&value.to_lowercase());
+                    offset += len;
+                }
+                None => {
+                    romanji.push(chars[offset]);
+                    offset += 1;
+                }
+            }
+        }
+        romanji
+    }
+}
+
+fn collect_kana(node: &Kana, kana: &mut Vec<String>) {
+    if let Some(value) = &node.value
+        && !kana.contains(value)
+    {
+        kana.push(value.clone());
+    }
+    for child in &node.next {
+        collect_kana(child, kana);
+    }
+}
+
+fn collect_romanji(node: &Kana, prefix: &mut String, romanji: &mut HashMap<String, String>) {
+    prefix.push(node.key);
+    if let Some(value) = &node.value {
+        // the first rule for a kana wins, like in convert
+        romanji
+            .entry(value.clone())
+            .or_insert_with(|| prefix.clone());
+    }
+    for child in &node.next {
+        collect_romanji(child, prefix, romanji);
     }
+    prefix.pop();
+}
+
+// map hiragana to the matching katakana, everything else is kept
+pub fn to_katakana(kana: &str) -> String {
+    kana.chars()
+        .map(|value| match value {
+            'ぁ'..='ゖ' | 'ゝ' | 'ゞ' => char::from_u32(value as u32 + 0x60).unwrap_or(value),
+            _ => value,
+        })
+        .collect()
+}
+
+// map katakana to the matching hiragana, everything else is kept
+pub fn to_hiragana(kana: &str) -> String {
+    kana.chars()
+        .map(|value| match value {
+            'ァ'..='ヶ' | 'ヽ' | 'ヾ' => char::from_u32(value as u32 - 0x60).unwrap_or(value),
+            _ => value,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -137,7 +236,8 @@ mod tests {
 
     #[test]
     fn test_converter() {
-        let converter = RomanjiToKanaConverter::new();
+        let converter =
+            RomanjiToKanaConverter::from_file(Path::new("resources/kana.json")).unwrap();
         let test_cases = vec![
             ("a", "あ"),
             ("i", "い"),
@@ -457,4 +557,66 @@ mod tests {
             assert_eq!(result, expected, "Failed for romanji: {}", romanji);
         }
     }
+
+    #[test]
+    fn test_to_katakana() {
+        let test_cases = vec![
+            ("あいうえお", "アイウエオ"),
+            ("きゃっと", "キャット"),
+            ("ゔぁ", "ヴァ"),
+            ("テレビ", "テレビ"),
+            ("ー。 x", "ー。 x"),
+        ];
+
+        for (kana, expected) in test_cases {
+            assert_eq!(to_katakana(kana), expected, "Failed for kana: {}", kana);
+        }
+    }
+
+    #[test]
+    fn test_to_hiragana() {
+        let test_cases = vec![
+            ("アイウエオ", "あいうえお"),
+            ("キャット", "きゃっと"),
+            ("テレビ", "てれび"),
+            ("コーヒー x", "こーひー x"),
+        ];
+
+        for (kana, expected) in test_cases {
+            assert_eq!(to_hiragana(kana), expected, "Failed for kana: {}", kana);
+        }
+    }
+
+    #[test]
+    fn test_to_romanji() {
+        let converter =
+            RomanjiToKanaConverter::from_file(Path::new("resources/kana.json")).unwrap();
+        let test_cases = vec![
+            ("にほん", "nihon"),
+            ("きょうしつ", "kyoushitsu"),
+            ("がっこう", "gakkou"),
+            ("コーヒー", "ko-hi-"),
+            ("おはよう ございます", "ohayou gozaimasu"),
+            ("すみません。", "sumimasen."),
+            ("日本ご", "日本go"),
+        ];
+
+        for (kana, expected) in test_cases {
+            assert_eq!(
+                converter.to_romanji(kana),
+                expected,
+                "Failed for kana: {}",
+                kana
+            );
+            // converting back gives the hiragana again
+            if expected.is_ascii() {
+                assert_eq!(
+                    converter.convert(&String::from(expected)),
+                    to_hiragana(kana),
+                    "Failed for kana: {}",
+                    kana
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::kana_converter::{RomanjiToKanaConverter, to_hiragana};
+use crate::lessons::{Lesson, Phrase, Section};
+
+// kana with a dakuten or handakuten
+const VOICED: &str = "がぎぐげござじずぜぞだぢづでどばびぶべぼぱぴぷぺぽゔ";
+
+// which kana a kana drill asks. the scripts pick hiragana and katakana,
+// dakuten and yōon add to the basic kana of the picked scripts
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KanaPool {
+    Hiragana,
+    Katakana,
+    Dakuten,
+    #[serde(alias = "yōon")]
+    Yoon,
+}
+
+impl KanaPool {
+    pub fn label(self) -> &'static str {
+        match self {
+            KanaPool::Hiragana => "hiragana",
+            KanaPool::Katakana => "katakana",
+            KanaPool::Dakuten => "dakuten",
+            KanaPool::Yoon => "yōon",
+        }
+    }
+}
+
+impl FromStr for KanaPool {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "hiragana" => Ok(KanaPool::Hiragana),
+            "katakana" => Ok(KanaPool::Katakana),
+            "dakuten" => Ok(KanaPool::Dakuten),
+            "yoon" | "yōon" => Ok(KanaPool::Yoon),
+            _ => Err(format!(
+                "invalid kana '{value}', expected hiragana, katakana, dakuten or yoon"
+            )),
+        }
+    }
+}
+
+// the pools the lesson screen cycles through
+const PRESETS: [&[KanaPool]; 7] = [
+    &[KanaPool::Hiragana],
+    &[KanaPool::Hiragana, KanaPool::Dakuten],
+    &[KanaPool::Hiragana, KanaPool::Dakuten, KanaPool::Yoon],
+    &[KanaPool::Katakana],
+    &[KanaPool::Katakana, KanaPool::Dakuten],
+    &[KanaPool::Katakana, KanaPool::Dakuten, KanaPool::Yoon],
+    &[
+        KanaPool::Hiragana,
+        KanaPool::Katakana,
+        KanaPool::Dakuten,
+        KanaPool::Yoon,
+    ],
+];
+
+// no pools at all asks the basic hiragana
+pub fn normalize(pools: &[KanaPool]) -> Vec<KanaPool> {
+    let mut pools = pools.to_vec();
+    pools.sort();
+    pools.dedup();
+    if !pools.contains(&KanaPool::Hiragana) && !pools.contains(&KanaPool::Katakana) {
+        pools.insert(0, KanaPool::Hiragana);
+    }
+    pools
+}
+
+// the preset after the given pools, pools set on the command line start
+// over at the first one
+pub fn next_pools(pools: &[KanaPool]) -> Vec<KanaPool> {
+    let pools = normalize(pools);
+    let next = PRESETS
+        .iter()
+        .position(|preset| *preset == pools.as_slice())
+        .map_or(0, |idx| (idx + 1) % PRESETS.len());
+    PRESETS[next].to_vec()
+}
+
+pub fn pools_label(pools: &[KanaPool]) -> String {
+    normalize(pools)
+        .iter()
+        .map(|pool| pool.label())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+// katakana or hiragana, whichever the kana are written in
+pub fn script_label(kana: &str) -> &'static str {
+    if kana.chars().any(is_katakana) {
+        KanaPool::Katakana.label()
+    } else {
+        KanaPool::Hiragana.label()
+    }
+}
+
+// a lesson per picked script with a section for the basic kana, the
+// dakuten and the yōon. every kana is a phrase with its romanji as the
+// english, kept in the progress under "kana:<kana>"
+pub fn lessons(converter: &RomanjiToKanaConverter, pools: &[KanaPool]) -> Vec<Lesson> {
+    let pools = normalize(pools);
+    let scripts = [
+        (KanaPool::Hiragana, "Hiragana", "ひらがな"),
+        (KanaPool::Katakana, "Katakana", "カタカナ"),
+    ];
+    let all_kana = converter.kana();
+    let mut lessons = Vec::<Lesson>::new();
+    for (index, (script, name_en, name_jp)) in scripts.into_iter().enumerate() {
+        if !pools.contains(&script) {
+            continue;
+        }
+        let mut sections = Vec::<Section>::new();
+        for (kind, name) in [
+            (None, "basic"),
+            (Some(KanaPool::Dakuten), "dakuten"),
+            (Some(KanaPool::Yoon), "yōon"),
+        ] {
+            if kind.is_some_and(|kind| !pools.contains(&kind)) {
+                continue;
+            }
+            let phrases: Vec<Phrase> = all_kana
+                .iter()
+                .filter(|kana| script_label(kana) == script.label())
+                .filter(|kana| {
+                    classify(kana).is_some_and(|(voiced, yoon)| {
+                        let section = if yoon {
+                            Some(KanaPool::Yoon)
+                        } else if voiced {
+                            Some(KanaPool::Dakuten)
+                        } else {
+                            None
+                        };
+                        // voiced yōon like ぎゃ need dakuten too
+                        section == kind && (!voiced || pools.contains(&KanaPool::Dakuten))
+                    })
+                })
+                .map(|kana| Phrase {
+                    id: format!("kana:{kana}"),
+                    en: converter.to_romanji(kana),
+                    jp: kana.clone(),
+                    ..Phrase::default()
+                })
+                .collect();
+            if !phrases.is_empty() {
+                sections.push(Section {
+                    name: String::from(name),
+                    phrases,
+                });
+            }
+        }
+        lessons.push(Lesson {
+            index,
+            name_en: String::from(name_en),
+            name_jp: String::from(name_jp),
+            sections,
+        });
+    }
+    lessons
+}
+
+// (voiced, yōon) of a single kana or a kana with a small ya, yu or yo.
+// None for the rest of the rules: small tsu doubling, long vowel marks,
+// punctuation and the katakana made for foreign sounds like ティ or テュ
+fn classify(kana: &str) -> Option<(bool, bool)> {
+    if !kana
+        .chars()
+        .all(|value| is_hiragana(value) || is_katakana(value))
+    {
+        return None;
+    }
+    let chars: Vec<char> = to_hiragana(kana).chars().collect();
+    let first = *chars.first()?;
+    if is_small(first) {
+        return None;
+    }
+    let voiced = VOICED.contains(first);
+    match chars.as_slice() {
+        [_] => Some((voiced, false)),
+        [_, 'ゃ' | 'ゅ' | 'ょ'] if "きしちにひみりぎじぢびぴ".contains(first) => {
+            Some((voiced, true))
+        }
+        _ => None,
+    }
+}
+
+fn is_small(value: char) -> bool {
+    "ぁぃぅぇぉっゃゅょゎ".contains(value)
+}
+
+fn is_hiragana(value: char) -> bool {
+    matches!(value, 'ぁ'..='ゖ')
+}
+
+fn is_katakana(value: char) -> bool {
+    matches!(value, 'ァ'..='ヺ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn kana(lessons: &[Lesson], name: &str) -> Vec<String> {
+        lessons
+            .iter()
+            .flat_map(|lesson| &lesson.sections)
+            .filter(|section| section.name == name)
+            .flat_map(|section| &section.phrases)
+            .map(|phrase| phrase.jp.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_lessons() {
+        let converter =
+            RomanjiToKanaConverter::from_file(Path::new("resources/kana.json")).unwrap();
+        // (pools, lesson names, basic, dakuten and yōon kana counts)
+        let test_cases = vec![
+            (vec![], vec!["Hiragana"], 46, 0, 0),
+            (vec![KanaPool::Hiragana], vec!["Hiragana"], 46, 0, 0),
+            (vec![KanaPool::Katakana], vec!["Katakana"], 46, 0, 0),
+            (vec![KanaPool::Dakuten], vec!["Hiragana"], 46, 25, 0),
+            (vec![KanaPool::Yoon], vec!["Hiragana"], 46, 0, 21),
+            (
+                vec![KanaPool::Hiragana, KanaPool::Dakuten, KanaPool::Yoon],
+                vec!["Hiragana"],
+                46,
+                25,
+                33,
+            ),
+            (
+                vec![
+                    KanaPool::Hiragana,
+                    KanaPool::Katakana,
+                    KanaPool::Dakuten,
+                    KanaPool::Yoon,
+                ],
+                vec!["Hiragana", "Katakana"],
+                92,
+                51,
+                66,
+            ),
+        ];
+        for (pools, names, basic, dakuten, yoon) in test_cases {
+            let lessons = lessons(&converter, &pools);
+            let lesson_names: Vec<&str> = lessons
+                .iter()
+                .map(|lesson| lesson.name_en.as_str())
+                .collect();
+            assert_eq!(lesson_names, names, "Failed for pools: {:?}", pools);
+            assert_eq!(
+                kana(&lessons, "basic").len(),
+                basic,
+                "Failed for pools: {:?}",
+                pools
+            );
+            assert_eq!(
+                kana(&lessons, "dakuten").len(),
+                dakuten,
+                "Failed for pools: {:?}",
+                pools
+            );
+            assert_eq!(
+                kana(&lessons, "yōon").len(),
+                yoon,
+                "Failed for pools: {:?}",
+                pools
+            );
+        }
+
+        let lessons = lessons(&converter, &[KanaPool::Katakana, KanaPool::Dakuten]);
+        let phrase = &lessons[0].sections[1].phrases[0];
+        assert_eq!(
+            (phrase.id.as_str(), phrase.en.as_str(), phrase.jp.as_str()),
+            ("kana:ガ", "ga", "ガ")
+        );
+        assert!(kana(&lessons, "basic").contains(&String::from("ン")));
+        assert!(kana(&lessons, "dakuten").contains(&String::from("ヴ")));
+        assert!(!kana(&lessons, "basic").contains(&String::from("ー")));
+    }
+
+    #[test]
+    fn test_next_pools() {
+        let test_cases = vec![
+            (vec![], vec![KanaPool::Hiragana, KanaPool::Dakuten]),
+            (
+                vec![KanaPool::Dakuten, KanaPool::Hiragana],
+                vec![KanaPool::Hiragana, KanaPool::Dakuten, KanaPool::Yoon],
+            ),
+            (
+                vec![
+                    KanaPool::Hiragana,
+                    KanaPool::Katakana,
+                    KanaPool::Dakuten,
+                    KanaPool::Yoon,
+                ],
+                vec![KanaPool::Hiragana],
+            ),
+            // not a preset
+            (
+                vec![KanaPool::Hiragana, KanaPool::Yoon],
+                vec![KanaPool::Hiragana],
+            ),
+        ];
+        for (pools, expected) in test_cases {
+            assert_eq!(
+                next_pools(&pools),
+                expected,
+                "Failed for pools: {:?}",
+                pools
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let test_cases = vec![
+            ("hiragana", Some(KanaPool::Hiragana)),
+            ("katakana", Some(KanaPool::Katakana)),
+            ("dakuten", Some(KanaPool::Dakuten)),
+            ("yoon", Some(KanaPool::Yoon)),
+            ("yōon", Some(KanaPool::Yoon)),
+            ("kanji", None),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(
+                value.parse::<KanaPool>().ok(),
+                expected,
+                "Failed for value: {}",
+                value
+            );
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs;
+use std::path::Path;
 
 #[derive(Debug, Deserialize)]
 struct Head {
@@ -32,24 +35,24 @@ impl<'a> Phrase<'a> {
 
     fn compare(&mut self, node: &Kanji) -> bool {
         let first = self.hiragana.chars().nth(self.offset);
-        if let Some(first) = &first {
-            if node.key == *first {
-                self.offset += 1;
-                if self.done()
-                    && let Some(value) = &node.value
-                {
-                    self.kanji = value.clone();
-                } else if self.done() {
-                    return false;
-                }
-                return true;
+        if let Some(first) = &first
+            && node.key == *first
+        {
+            self.offset += 1;
+            if self.done()
+                && let Some(value) = &node.value
+            {
+                self.kanji = value.clone();
+            } else if self.done() {
+                return false;
             }
+            return true;
         }
-        return false;
+        false
     }
 
     fn done(&self) -> bool {
-        return self.offset == self.hiragana.chars().count();
+        self.offset == self.hiragana.chars().count()
     }
 
     fn reset(&mut self) {
@@ -57,7 +60,7 @@ impl<'a> Phrase<'a> {
     }
 
     fn get_kanji(self) -> Vec<char> {
-        return self.kanji;
+        self.kanji
     }
 }
 
@@ -66,14 +69,15 @@ pub struct HiragaToKanjiConverter {
 }
 
 impl HiragaToKanjiConverter {
-    pub fn new() -> Self {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
         // read kanji rules json
-        let json =
-            fs::read_to_string("resources/kanji.json").expect("couldnt read resources/kanji.json");
+        let json = fs::read_to_string(path)
+            .map_err(|err| format!("couldnt read {}: {err}", path.display()))?;
 
-        HiragaToKanjiConverter {
-            head: serde_json::from_str::<Head>(&json).unwrap(),
-        }
+        Ok(HiragaToKanjiConverter {
+            head: serde_json::from_str::<Head>(&json)
+                .map_err(|err| format!("couldnt parse {}: {err}", path.display()))?,
+        })
     }
 
     fn convert_phrase(&self, phrase: &mut Phrase) {
@@ -89,7 +93,7 @@ impl HiragaToKanjiConverter {
     }
 
     fn iterate_kanji(&self, node: &Kanji, phrase: &mut Phrase) -> bool {
-        let mut matched = phrase.compare(&node);
+        let mut matched = phrase.compare(node);
         if matched && !phrase.done() {
             for child in &node.next {
                 matched = self.iterate_kanji(child, phrase);
@@ -98,16 +102,121 @@ impl HiragaToKanjiConverter {
                 }
             }
         }
-        return matched;
+        matched
     }
 
     pub fn convert(&self, hiragana: &String) -> Vec<char> {
-        let mut phrase = Phrase::new(&hiragana);
+        let mut phrase = Phrase::new(hiragana);
         self.convert_phrase(&mut phrase);
-        return phrase.get_kanji();
+        phrase.get_kanji()
+    }
+
+    // every reading of every kanji, in the order of the rules
+    pub fn readings(&self) -> HashMap<char, Vec<String>> {
+        let mut readings = HashMap::<char, Vec<String>>::new();
+        let mut kana = String::new();
+        for root in &self.head.roots {
+            collect_readings(root, &mut kana, &mut readings);
+        }
+        readings
+    }
+
+    // split kana into the longest spans with kanji candidates, kana without
+    // candidates are kept together in their own segments
+    pub fn segment(&self, hiragana: &str) -> Vec<Segment> {
+        let chars: Vec<char> = hiragana.chars().collect();
+        let mut segments = Vec::<Segment>::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let mut matched = None;
+            for end in (start + 1..=chars.len()).rev() {
+                // small kana belong to the kana before them
+                if end < chars.len() && is_small_kana(chars[end]) {
+                    continue;
+                }
+                let kana: String = chars[start..end].iter().collect();
+                let candidates = self.convert(&kana);
+                if !candidates.is_empty() {
+                    matched = Some(Segment { kana, candidates });
+                    break;
+                }
+            }
+            match matched {
+                Some(segment) => {
+                    start += segment.kana.chars().count();
+                    segments.push(segment);
+                }
+                None => {
+                    match segments.last_mut() {
+                        Some(segment) if segment.candidates.is_empty() => {
+                            segment.kana.push(chars[start]);
+                        }
+                        _ => segments.push(Segment {
+                            kana: chars[start].to_string(),
+                            candidates: Vec::new(),
+                        }),
+                    }
+                    start += 1;
+                }
+            }
+        }
+        segments
     }
 }
 
+fn collect_readings(node: &Kanji, kana: &mut String, readings: &mut HashMap<char, Vec<String>>) {
+    kana.push(node.key);
+    for &kanji in node.value.iter().flatten() {
+        readings.entry(kanji).or_default().push(kana.clone());
+    }
+    for child in &node.next {
+        collect_readings(child, kana, readings);
+    }
+    kana.pop();
+}
+
+fn is_small_kana(value: char) -> bool {
+    matches!(
+        value,
+        'ゃ' | 'ゅ'
+            | 'ょ'
+            | 'ぁ'
+            | 'ぃ'
+            | 'ぅ'
+            | 'ぇ'
+            | 'ぉ'
+            | 'ャ'
+            | 'ュ'
+            | 'ョ'
+            | 'ァ'
+            | 'ィ'
+            | 'ゥ'
+            | 'ェ'
+            | 'ォ'
+    )
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    pub kana: String,
+    pub candidates: Vec<char>,
+}
+
+// the first candidate of every segment. single kana segments are mostly
+// particles and okurigana, their candidates would turn them into random
+// numerals, so they stay kana unless they are all there is
+pub fn best_guess(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment.candidates.first() {
+            Some(kanji) if segments.len() == 1 || segment.kana.chars().count() > 1 => {
+                kanji.to_string()
+            }
+            _ => segment.kana.clone(),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -115,7 +224,8 @@ mod tests {
 
     #[test]
     fn test_converter() {
-        let converter = HiragaToKanjiConverter::new();
+        let converter =
+            HiragaToKanjiConverter::from_file(Path::new("resources/kanji.json")).unwrap();
         let test_cases = vec![
             ("いち", vec!['一']),
             ("いっ", vec!['一']),
@@ -192,4 +302,43 @@ mod tests {
             assert_eq!(result, expected, "Failed for romaji: {}", romaji);
         }
     }
+
+    #[test]
+    fn test_segment() {
+        let converter =
+            HiragaToKanjiConverter::from_file(Path::new("resources/kanji.json")).unwrap();
+        let segments = converter.segment("にほんご");
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    kana: String::from("に"),
+                    candidates: vec!['二', '日'],
+                },
+                Segment {
+                    kana: String::from("ほん"),
+                    candidates: vec!['本'],
+                },
+                Segment {
+                    kana: String::from("ご"),
+                    candidates: vec!['五'],
+                },
+            ]
+        );
+        assert_eq!(best_guess(&segments), "に本ご");
+        assert_eq!(best_guess(&converter.segment("に")), "二");
+
+        let segments = converter.segment("きょうはいちじ");
+        let segment_kana: Vec<&str> = segments
+            .iter()
+            .map(|segment| segment.kana.as_str())
+            .collect();
+        assert_eq!(segment_kana, vec!["きょうは", "いち", "じ"]);
+        assert!(segments[0].candidates.is_empty());
+
+        let segments = converter.segment("アメリカ");
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].candidates.is_empty());
+        assert!(converter.segment("").is_empty());
+    }
 }
@@ -0,0 +1,837 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::app::{Action, CurrentScreen, Move};
+
+// a key with the modifiers that have to be held, e.g. "shift+left"
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Key { code, modifiers }
+    }
+
+    fn plain(code: KeyCode) -> Self {
+        Key::new(code, KeyModifiers::NONE)
+    }
+
+    // modifiers have to match exactly, except shift on a typed char as it
+    // is already part of the char
+    fn matches(&self, key: &KeyEvent) -> bool {
+        let mut expected = self.modifiers;
+        let mut modifiers = key.modifiers;
+        if let KeyCode::Char(_) = key.code {
+            expected.remove(KeyModifiers::SHIFT);
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self.code == key.code && modifiers == expected
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = value;
+        // "+" on its own or at the end is the plus key, not a separator
+        while let Some((modifier, rest)) = name.split_once('+')
+            && !rest.is_empty()
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{value}'")),
+            };
+            name = rest;
+        }
+        let code = match name.to_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            lowercase => match lowercase.strip_prefix('f').map(str::parse::<u8>) {
+                Some(Ok(number)) => KeyCode::F(number),
+                _ => {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(value), None) => KeyCode::Char(value),
+                        _ => return Err(format!("unknown key '{value}'")),
+                    }
+                }
+            },
+        };
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(value) => write!(f, "{value}"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub screen: CurrentScreen,
+    pub key: Key,
+    pub action: Action,
+}
+
+// every rebindable key, typed chars on the review screen and any key on
+// the welcome screen are handled outside the table
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use CurrentScreen::*;
+        let table = [
+            (Welcome, Key::plain(KeyCode::Esc), Back),
+            (BookSelect, Key::plain(KeyCode::Esc), Back),
+            (BookSelect, Key::plain(KeyCode::Enter), Continue),
+            (BookSelect, Key::plain(KeyCode::Up), MoveCursor(Move::Up)),
+            (
+                BookSelect,
+                Key::plain(KeyCode::Down),
+                MoveCursor(Move::Down),
+            ),
+            (BookSelect, Key::plain(KeyCode::Char('t')), CycleTheme),
+            (LessonSelect, Key::plain(KeyCode::Esc), Back),
+            (LessonSelect, Key::plain(KeyCode::Enter), StartSession),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char(' ')),
+                ToggleSelection,
+            ),
+            (LessonSelect, Key::plain(KeyCode::Char('c')), ClearSelection),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char('d')),
+                CycleDirectionMode,
+            ),
+            (LessonSelect, Key::plain(KeyCode::Char('t')), CycleTheme),
+            (LessonSelect, Key::plain(KeyCode::Char('f')), ToggleFurigana),
+            (LessonSelect, Key::plain(KeyCode::Char('m')), CycleDrill),
+            (LessonSelect, Key::plain(KeyCode::Char('k')), CycleKanaPools),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char('b')),
+                BrowseVocabulary,
+            ),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char('/')),
+                SearchVocabulary,
+            ),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char('+')),
+                AdjustDirectionWeight(10),
+            ),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Char('-')),
+                AdjustDirectionWeight(-10),
+            ),
+            (LessonSelect, Key::plain(KeyCode::Up), MoveCursor(Move::Up)),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Down),
+                MoveCursor(Move::Down),
+            ),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Left),
+                MoveCursor(Move::Left),
+            ),
+            (
+                LessonSelect,
+                Key::plain(KeyCode::Right),
+                MoveCursor(Move::Right),
+            ),
+            (Review, Key::plain(KeyCode::Esc), Back),
+            (Review, Key::plain(KeyCode::Enter), Submit),
+            (Review, Key::plain(KeyCode::Tab), ConvertSpan),
+            (
+                Review,
+                Key::new(KeyCode::Left, KeyModifiers::SHIFT),
+                ExtendSelection(Move::Left),
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Right, KeyModifiers::SHIFT),
+                ExtendSelection(Move::Right),
+            ),
+            (Review, Key::plain(KeyCode::Up), MoveCursor(Move::Up)),
+            (Review, Key::plain(KeyCode::Down), MoveCursor(Move::Down)),
+            (Review, Key::plain(KeyCode::Left), MoveCursor(Move::Left)),
+            (Review, Key::plain(KeyCode::Right), MoveCursor(Move::Right)),
+            (
+                Review,
+                Key::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+                ClearInput,
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Backspace, KeyModifiers::CONTROL),
+                DeleteWord,
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Backspace, KeyModifiers::ALT),
+                DeleteWord,
+            ),
+            (Review, Key::plain(KeyCode::Backspace), DeleteChar),
+            (
+                Review,
+                Key::new(KeyCode::Char('k'), KeyModifiers::CONTROL),
+                MarkKnown,
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL),
+                MarkHard,
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Char('f'), KeyModifiers::CONTROL),
+                FlagBadData,
+            ),
+            (
+                Review,
+                Key::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+                ToggleFurigana,
+            ),
+            (Browse, Key::plain(KeyCode::Esc), Back),
+            (Browse, Key::plain(KeyCode::Up), MoveCursor(Move::Up)),
+            (Browse, Key::plain(KeyCode::Down), MoveCursor(Move::Down)),
+            (
+                Browse,
+                Key::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+                ClearInput,
+            ),
+            (Browse, Key::plain(KeyCode::Backspace), DeleteChar),
+            (Search, Key::plain(KeyCode::Esc), Back),
+            (Search, Key::plain(KeyCode::Enter), StartSession),
+            (Search, Key::plain(KeyCode::Up), MoveCursor(Move::Up)),
+            (Search, Key::plain(KeyCode::Down), MoveCursor(Move::Down)),
+            (
+                Search,
+                Key::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+                ClearInput,
+            ),
+            (Search, Key::plain(KeyCode::Backspace), DeleteChar),
+            // ? is part of english answers so it only opens help outside review
+            (Welcome, Key::plain(KeyCode::Char('?')), ToggleHelp),
+            (BookSelect, Key::plain(KeyCode::Char('?')), ToggleHelp),
+            (LessonSelect, Key::plain(KeyCode::Char('?')), ToggleHelp),
+            (Welcome, Key::plain(KeyCode::F(1)), ToggleHelp),
+            (BookSelect, Key::plain(KeyCode::F(1)), ToggleHelp),
+            (LessonSelect, Key::plain(KeyCode::F(1)), ToggleHelp),
+            (Review, Key::plain(KeyCode::F(1)), ToggleHelp),
+            (Browse, Key::plain(KeyCode::F(1)), ToggleHelp),
+            (Search, Key::plain(KeyCode::F(1)), ToggleHelp),
+        ];
+        Keymap {
+            bindings: table
+                .into_iter()
+                .map(|(screen, key, action)| Binding {
+                    screen,
+                    key,
+                    action,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    // translate a key press into an action for the current screen
+    pub fn action_for(&self, screen: CurrentScreen, key: KeyEvent) -> Option<Action> {
+        // terminals with keyboard enhancement also report releases and repeats
+        match key.kind {
+            KeyEventKind::Press => self.press_action(screen, key),
+            KeyEventKind::Repeat => self.press_action(screen, key).filter(Action::is_repeatable),
+            KeyEventKind::Release => None,
+        }
+    }
+
+    fn press_action(&self, screen: CurrentScreen, key: KeyEvent) -> Option<Action> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        // always a way out, whatever the config says
+        if control && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        // the binding needing the most modifiers wins, so shift+left beats left
+        let binding = self
+            .bindings
+            .iter()
+            .filter(|binding| binding.screen == screen && binding.key.matches(&key))
+            .max_by_key(|binding| binding.key.modifiers.bits().count_ones());
+        if let Some(binding) = binding {
+            return Some(binding.action);
+        }
+        match (screen, key.code) {
+            (CurrentScreen::Welcome, _) => Some(Action::Continue),
+            (
+                CurrentScreen::Review | CurrentScreen::Browse | CurrentScreen::Search,
+                KeyCode::Char(value),
+            ) if !control && !alt => Some(Action::Input(value)),
+            _ => None,
+        }
+    }
+
+    // replaces the keys of an action, taking the keys away from other actions
+    pub fn bind(&mut self, screen: CurrentScreen, action: Action, keys: &[Key]) {
+        self.bindings.retain(|binding| {
+            binding.screen != screen || (binding.action != action && !keys.contains(&binding.key))
+        });
+        self.bindings.extend(keys.iter().map(|&key| Binding {
+            screen,
+            key,
+            action,
+        }));
+    }
+
+    // keys and what they do on a screen, in table order with the keys of
+    // an action grouped together
+    pub fn help(&self, screen: CurrentScreen) -> Vec<(String, &'static str)> {
+        let mut actions = Vec::<(Action, Vec<String>)>::new();
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.screen == screen)
+        {
+            match actions
+                .iter_mut()
+                .find(|(action, _)| *action == binding.action)
+            {
+                Some((_, keys)) => keys.push(binding.key.to_string()),
+                None => actions.push((binding.action, vec![binding.key.to_string()])),
+            }
+        }
+        let mut help: Vec<(String, &'static str)> = match screen {
+            CurrentScreen::Welcome => vec![(String::from("any key"), "go to the lesson list")],
+            CurrentScreen::BookSelect | CurrentScreen::LessonSelect => Vec::new(),
+            CurrentScreen::Review => vec![(
+                String::from("letters"),
+                "type the answer in romaji, uppercase for katakana",
+            )],
+            CurrentScreen::Browse => {
+                vec![(String::from("letters"), "search english, romaji or kana")]
+            }
+            CurrentScreen::Search => vec![(
+                String::from("letters"),
+                "search every lesson in english, romaji, kana or kanji",
+            )],
+        };
+        help.extend(actions.into_iter().filter_map(|(action, keys)| {
+            let (_, text) = description(screen, action)?;
+            Some((keys.join(" / "), text))
+        }));
+        help.push((String::from("Ctrl+c"), "quit from anywhere"));
+        help
+    }
+
+    // first key of an action, for hints next to what it changes
+    pub fn key_for(&self, screen: CurrentScreen, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.screen == screen && binding.action == action)
+            .map(|binding| binding.key.to_string())
+    }
+
+    // the few keys worth showing at the bottom of a screen
+    pub fn hints(&self, screen: CurrentScreen) -> Vec<(String, &'static str)> {
+        let actions: &[Action] = match screen {
+            CurrentScreen::Welcome => &[Action::ToggleHelp, Action::Back],
+            CurrentScreen::BookSelect => &[
+                Action::Continue,
+                Action::MoveCursor(Move::Down),
+                Action::ToggleHelp,
+                Action::Back,
+            ],
+            CurrentScreen::LessonSelect => &[
+                Action::StartSession,
+                Action::ToggleSelection,
+                Action::MoveCursor(Move::Right),
+                Action::BrowseVocabulary,
+                Action::SearchVocabulary,
+                Action::ToggleHelp,
+                Action::Back,
+            ],
+            CurrentScreen::Review => &[
+                Action::Submit,
+                Action::ConvertSpan,
+                Action::ExtendSelection(Move::Right),
+                Action::MoveCursor(Move::Down),
+                Action::ToggleHelp,
+                Action::Back,
+            ],
+            CurrentScreen::Browse => &[
+                Action::MoveCursor(Move::Down),
+                Action::ClearInput,
+                Action::ToggleHelp,
+                Action::Back,
+            ],
+            CurrentScreen::Search => &[
+                Action::StartSession,
+                Action::MoveCursor(Move::Down),
+                Action::ClearInput,
+                Action::ToggleHelp,
+                Action::Back,
+            ],
+        };
+        let mut hints = Vec::<(String, &'static str)>::new();
+        if screen == CurrentScreen::Welcome {
+            hints.push((String::from("any key"), "start"));
+        }
+        for &action in actions {
+            if let Some(key) = self.key_for(screen, action)
+                && let Some((label, _)) = description(screen, action)
+            {
+                hints.push((key, label));
+            }
+        }
+        hints
+    }
+}
+
+// a short label for the hint bar and a sentence for the help overlay
+fn description(screen: CurrentScreen, action: Action) -> Option<(&'static str, &'static str)> {
+    use CurrentScreen::*;
+    let description = match (screen, action) {
+        (_, Action::Quit) => ("quit", "quit the keyboard"),
+        // ? is typed into the answer or the search there
+        (Review | Browse | Search, Action::ToggleHelp) => (
+            "help (? is typed)",
+            "show or hide this help, ? is typed as text on this screen",
+        ),
+        (_, Action::ToggleHelp) => ("help", "show or hide this help"),
+        (Welcome | BookSelect, Action::Back) => ("quit", "quit the keyboard"),
+        (LessonSelect, Action::Back) => (
+            "quit",
+            "quit, or go back to the books when there are several",
+        ),
+        (BookSelect, Action::Continue) => ("open", "open the highlighted book"),
+        (BookSelect, Action::MoveCursor(Move::Up)) => ("up", "move up"),
+        (BookSelect, Action::MoveCursor(Move::Down)) => ("down", "move down"),
+        (BookSelect, Action::CycleTheme) => ("theme", "switch to the next colour theme"),
+        (LessonSelect, Action::StartSession) => (
+            "start",
+            "practice the selection, or the highlighted lesson or section",
+        ),
+        (LessonSelect, Action::ToggleSelection) => {
+            ("select", "add or remove the highlighted lesson or section")
+        }
+        (LessonSelect, Action::ClearSelection) => ("clear", "clear the selection"),
+        (LessonSelect, Action::CycleDirectionMode) => {
+            ("direction", "change which way phrases are translated")
+        }
+        (LessonSelect, Action::AdjustDirectionWeight(10)) => {
+            ("more JP", "ask EN → JP more often in random mode")
+        }
+        (LessonSelect, Action::AdjustDirectionWeight(-10)) => {
+            ("more EN", "ask JP → EN more often in random mode")
+        }
+        (LessonSelect, Action::CycleTheme) => ("theme", "switch to the next colour theme"),
+        (LessonSelect, Action::CycleDrill) => (
+            "drill",
+            "change the drill: translation, kanji readings, writing or kana",
+        ),
+        (LessonSelect, Action::CycleKanaPools) => {
+            ("kana", "practice kana, again to change which kana")
+        }
+        (LessonSelect | Review, Action::ToggleFurigana) => {
+            ("readings", "show or hide the readings above kanji")
+        }
+        (LessonSelect, Action::BrowseVocabulary) => (
+            "browse",
+            "browse the vocabulary of the highlighted lesson or section",
+        ),
+        (LessonSelect, Action::MoveCursor(Move::Up)) => ("up", "move up"),
+        (LessonSelect, Action::MoveCursor(Move::Down)) => ("down", "move down"),
+        (LessonSelect, Action::MoveCursor(Move::Left)) => ("lessons", "go back to the lessons"),
+        (LessonSelect, Action::MoveCursor(Move::Right)) => {
+            ("sections", "pick sections of the lesson")
+        }
+        (Review, Action::Back) => ("end", "end the session"),
+        (Review, Action::Submit) => ("submit", "check the answer and ask the next question"),
+        (Review, Action::ConvertSpan) => (
+            "convert",
+            "turn the highlighted kana into the chosen kanji, again to undo",
+        ),
+        (Review, Action::ExtendSelection(Move::Left)) => ("shorter", "highlight one kana less"),
+        (Review, Action::ExtendSelection(Move::Right)) => ("longer", "highlight one more kana"),
+        (Review, Action::MoveCursor(Move::Left)) => ("left", "move the highlight left"),
+        (Review, Action::MoveCursor(Move::Right)) => ("right", "move the highlight right"),
+        (Review, Action::MoveCursor(Move::Up)) => ("prev kanji", "choose the previous kanji"),
+        (Review, Action::MoveCursor(Move::Down)) => ("kanji", "choose the next kanji"),
+        (Review, Action::ClearInput) => ("clear", "clear the answer"),
+        (Review, Action::DeleteWord) => ("delete word", "delete the last word"),
+        (Review, Action::DeleteChar) => ("delete", "delete the last letter"),
+        (Review, Action::MarkKnown) => ("known", "stop asking this phrase, again to undo"),
+        (Review, Action::MarkHard) => ("hard", "ask this phrase twice as often, again to undo"),
+        (Review, Action::FlagBadData) => (
+            "flag",
+            "flag this phrase as wrong, with the typed text as a note",
+        ),
+        (LessonSelect, Action::SearchVocabulary) => {
+            ("search", "search the vocabulary of every lesson")
+        }
+        (Browse | Search, Action::Back) => ("back", "go back to the lessons"),
+        (Browse | Search, Action::MoveCursor(Move::Up)) => ("up", "move up"),
+        (Browse | Search, Action::MoveCursor(Move::Down)) => ("scroll", "move down"),
+        (Browse | Search, Action::ClearInput) => ("clear", "clear the search"),
+        (Browse | Search, Action::DeleteChar) => ("delete", "delete the last letter of the search"),
+        (Search, Action::StartSession) => {
+            ("drill", "practice the section of the highlighted phrase")
+        }
+        _ => return None,
+    };
+    Some(description)
+}
+
+// names of the rebindable actions in the config file
+const ACTION_NAMES: [(&str, Action); 30] = [
+    ("quit", Action::Quit),
+    ("back", Action::Back),
+    ("continue", Action::Continue),
+    ("start", Action::StartSession),
+    ("up", Action::MoveCursor(Move::Up)),
+    ("down", Action::MoveCursor(Move::Down)),
+    ("left", Action::MoveCursor(Move::Left)),
+    ("right", Action::MoveCursor(Move::Right)),
+    ("toggle", Action::ToggleSelection),
+    ("clear-selection", Action::ClearSelection),
+    ("direction", Action::CycleDirectionMode),
+    ("drill", Action::CycleDrill),
+    ("kana", Action::CycleKanaPools),
+    ("weight-up", Action::AdjustDirectionWeight(10)),
+    ("weight-down", Action::AdjustDirectionWeight(-10)),
+    ("theme", Action::CycleTheme),
+    ("browse", Action::BrowseVocabulary),
+    ("search", Action::SearchVocabulary),
+    ("submit", Action::Submit),
+    ("shrink", Action::ExtendSelection(Move::Left)),
+    ("extend", Action::ExtendSelection(Move::Right)),
+    ("convert", Action::ConvertSpan),
+    ("delete-char", Action::DeleteChar),
+    ("delete-word", Action::DeleteWord),
+    ("clear-input", Action::ClearInput),
+    ("known", Action::MarkKnown),
+    ("hard", Action::MarkHard),
+    ("flag", Action::FlagBadData),
+    ("furigana", Action::ToggleFurigana),
+    ("help", Action::ToggleHelp),
+];
+
+pub fn action_from_name(name: &str) -> Option<Action> {
+    ACTION_NAMES
+        .iter()
+        .find(|(action_name, _)| *action_name == name)
+        .map(|&(_, action)| action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_for() {
+        let test_cases = vec![
+            (
+                CurrentScreen::Welcome,
+                KeyCode::Char('x'),
+                KeyModifiers::NONE,
+                Some(Action::Continue),
+            ),
+            (
+                CurrentScreen::Welcome,
+                KeyCode::Esc,
+                KeyModifiers::NONE,
+                Some(Action::Back),
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('x'),
+                KeyModifiers::NONE,
+                None,
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Enter,
+                KeyModifiers::NONE,
+                Some(Action::StartSession),
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('t'),
+                KeyModifiers::NONE,
+                Some(Action::CycleTheme),
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('?'),
+                KeyModifiers::SHIFT,
+                Some(Action::ToggleHelp),
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('b'),
+                KeyModifiers::NONE,
+                Some(Action::BrowseVocabulary),
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('d'),
+                KeyModifiers::NONE,
+                Some(Action::CycleDirectionMode),
+            ),
+            // extra modifiers don't fall back to the plain binding
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('d'),
+                KeyModifiers::CONTROL,
+                None,
+            ),
+            (
+                CurrentScreen::LessonSelect,
+                KeyCode::Char('D'),
+                KeyModifiers::SHIFT,
+                None,
+            ),
+            (
+                CurrentScreen::Browse,
+                KeyCode::Char('b'),
+                KeyModifiers::NONE,
+                Some(Action::Input('b')),
+            ),
+            (
+                CurrentScreen::Browse,
+                KeyCode::Char('u'),
+                KeyModifiers::CONTROL,
+                Some(Action::ClearInput),
+            ),
+            (
+                CurrentScreen::Review,
+                KeyCode::Enter,
+                KeyModifiers::NONE,
+                Some(Action::Submit),
+            ),
+            (
+                CurrentScreen::Review,
+                KeyCode::Char(' '),
+                KeyModifiers::NONE,
+                Some(Action::Input(' ')),
+            ),
+            (
+                CurrentScreen::Review,
+                KeyCode::Left,
+                KeyModifiers::NONE,
+                Some(Action::MoveCursor(Move::Left)),
+            ),
+            (
+                CurrentScreen::Review,
+                KeyCode::Left,
+                KeyModifiers::SHIFT,
+                Some(Action::ExtendSelection(Move::Left)),
+            ),
+            (
+                CurrentScreen::Review,
+                KeyCode::Right,
+                KeyModifiers::SHIFT,
+                Some(Action::ExtendSelection(Move::Right)),
+            ),
+        ];
+
+        for (screen, code, modifiers, expected) in test_cases {
+            let result = Keymap::default().action_for(screen, KeyEvent::new(code, modifiers));
+            assert_eq!(
+                result, expected,
+                "Failed for key: {:?} {:?}",
+                code, modifiers
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_kinds() {
+        let test_cases = vec![
+            (
+                KeyCode::Esc,
+                KeyModifiers::NONE,
+                KeyEventKind::Release,
+                None,
+            ),
+            (
+                KeyCode::Enter,
+                KeyModifiers::NONE,
+                KeyEventKind::Release,
+                None,
+            ),
+            (
+                KeyCode::Enter,
+                KeyModifiers::NONE,
+                KeyEventKind::Repeat,
+                None,
+            ),
+            (KeyCode::Tab, KeyModifiers::NONE, KeyEventKind::Repeat, None),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::NONE,
+                KeyEventKind::Repeat,
+                Some(Action::DeleteChar),
+            ),
+            (
+                KeyCode::Char('a'),
+                KeyModifiers::NONE,
+                KeyEventKind::Repeat,
+                Some(Action::Input('a')),
+            ),
+            (
+                KeyCode::Char('c'),
+                KeyModifiers::CONTROL,
+                KeyEventKind::Press,
+                Some(Action::Quit),
+            ),
+            (
+                KeyCode::Char('u'),
+                KeyModifiers::CONTROL,
+                KeyEventKind::Press,
+                Some(Action::ClearInput),
+            ),
+            (
+                KeyCode::Char('x'),
+                KeyModifiers::ALT,
+                KeyEventKind::Press,
+                None,
+            ),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::CONTROL,
+                KeyEventKind::Press,
+                Some(Action::DeleteWord),
+            ),
+            (
+                KeyCode::Backspace,
+                KeyModifiers::ALT,
+                KeyEventKind::Press,
+                Some(Action::DeleteWord),
+            ),
+        ];
+
+        for (code, modifiers, kind, expected) in test_cases {
+            let key = KeyEvent::new_with_kind(code, modifiers, kind);
+            let result = Keymap::default().action_for(CurrentScreen::Review, key);
+            assert_eq!(result, expected, "Failed for key: {:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let test_cases = vec![
+            ("tab", Ok(Key::plain(KeyCode::Tab))),
+            ("f1", Ok(Key::plain(KeyCode::F(1)))),
+            ("f", Ok(Key::plain(KeyCode::Char('f')))),
+            ("+", Ok(Key::plain(KeyCode::Char('+')))),
+            (
+                "ctrl++",
+                Ok(Key::new(KeyCode::Char('+'), KeyModifiers::CONTROL)),
+            ),
+            (
+                "Ctrl+Shift+Left",
+                Ok(Key::new(
+                    KeyCode::Left,
+                    KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+                )),
+            ),
+            ("ab", Err(String::from("unknown key 'ab'"))),
+        ];
+        for (value, expected) in test_cases {
+            assert_eq!(value.parse::<Key>(), expected, "Failed for key: {}", value);
+        }
+        assert_eq!(
+            Key::new(KeyCode::Right, KeyModifiers::SHIFT).to_string(),
+            "Shift+→"
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        let mut keymap = Keymap::default();
+        let help = keymap.help(CurrentScreen::Review);
+        assert!(help.contains(&(
+            String::from("Tab"),
+            "turn the highlighted kana into the chosen kanji, again to undo"
+        )));
+        assert!(help.contains(&(
+            String::from("Ctrl+Backspace / Alt+Backspace"),
+            "delete the last word"
+        )));
+        assert!(
+            keymap
+                .hints(CurrentScreen::Review)
+                .contains(&(String::from("Tab"), "convert"))
+        );
+        assert!(
+            keymap
+                .hints(CurrentScreen::Review)
+                .contains(&(String::from("F1"), "help (? is typed)"))
+        );
+
+        // rebinding shows up in the help and hints straight away
+        keymap.bind(
+            CurrentScreen::Review,
+            Action::ConvertSpan,
+            &[Key::plain(KeyCode::Char(' '))],
+        );
+        let help = keymap.help(CurrentScreen::Review);
+        assert!(help.contains(&(
+            String::from("Space"),
+            "turn the highlighted kana into the chosen kanji, again to undo"
+        )));
+        assert!(!help.iter().any(|(keys, _)| keys == "Tab"));
+        assert!(
+            keymap
+                .hints(CurrentScreen::Review)
+                .contains(&(String::from("Space"), "convert"))
+        );
+    }
+}
@@ -1,23 +1,195 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
 use std::fs;
 use std::ops::Index;
+use std::path::Path;
 
+use crate::progress::Progress;
+use crate::tabular::{self, Delimiter};
+
+// version of the lessons.json schema, older files are migrated on load.
+// 1 had no version field and no phrase ids
+pub const VERSION: u32 = 2;
+
+// the drills key their questions with these, phrase ids can't use them
+pub const RESERVED_PREFIXES: [&str; 4] = ["reading:", "writing:", "kanji:", "kana:"];
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Book {
+    // shown on the book picker, the file name is used without one
+    pub title: Option<String>,
+    // book and edition the vocabulary comes from
+    pub source: Option<String>,
     pub lessons: Vec<Lesson>,
 }
 
 impl Book {
-    pub fn new() -> Self {
-        // read genki lesson vocab
-        let json = fs::read_to_string("resources/lessons.json")
-            .expect("couldnt read resources/lessons.json");
-        let lessons_wrapper: LessonsWrapper =
-            serde_json::from_str::<LessonsWrapper>(&json).unwrap();
-
-        Book {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        // read genki lesson vocab, spreadsheets are told apart by extension
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("couldnt read {}: {err}", path.display()))?;
+        let book = match Delimiter::from_path(path) {
+            Some(delimiter) => tabular::import(text.as_bytes(), delimiter),
+            None => Book::from_json(&text),
+        };
+        book.map_err(|err| format!("couldnt parse {}: {err}", path.display()).into())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let lessons_wrapper: LessonsWrapper = serde_json::from_str::<LessonsWrapper>(json)?;
+        let version = lessons_wrapper.version.unwrap_or(1);
+        if version > VERSION {
+            return Err(format!("version {version} needs a newer keyboard").into());
+        }
+        let mut book = Book {
+            title: lessons_wrapper.title,
+            source: lessons_wrapper.source,
             lessons: lessons_wrapper.lessons,
+        };
+        book.assign_ids(version);
+        book.check_ids()?;
+        Ok(book)
+    }
+
+    // phrases without an id in version 1 files get the key progress was
+    // saved under before there were ids, so migrated files keep their
+    // progress. in newer files a position could be the id of a phrase that
+    // moved down, so they get one made from the english and japanese
+    // instead. ids are never renumbered afterwards
+    pub fn assign_ids(&mut self, version: u32) {
+        let mut ids: HashSet<String> = self
+            .lessons
+            .iter()
+            .flat_map(|lesson| &lesson.sections)
+            .flat_map(|section| &section.phrases)
+            .map(|phrase| phrase.id.clone())
+            .collect();
+        for lesson in &mut self.lessons {
+            for (section_idx, section) in lesson.sections.iter_mut().enumerate() {
+                for (phrase_idx, phrase) in section.phrases.iter_mut().enumerate() {
+                    if !phrase.id.is_empty() {
+                        continue;
+                    }
+                    phrase.id = if version == 1 {
+                        Progress::key(lesson.index, section_idx, phrase_idx)
+                    } else {
+                        let hash = phrase_hash(phrase);
+                        (1..)
+                            .map(|count| match count {
+                                1 => hash.clone(),
+                                _ => format!("{hash}-{count}"),
+                            })
+                            .find(|id| !ids.contains(id))
+                            .unwrap()
+                    };
+                    ids.insert(phrase.id.clone());
+                }
+            }
         }
     }
+
+    // every loaded book goes through this, so validate doesn't have to
+    pub fn check_ids(&self) -> Result<(), Box<dyn Error>> {
+        let mut ids = HashSet::<&str>::new();
+        let phrases = self
+            .lessons
+            .iter()
+            .flat_map(|lesson| &lesson.sections)
+            .flat_map(|section| &section.phrases);
+        for phrase in phrases {
+            if !ids.insert(&phrase.id) {
+                return Err(format!("duplicate phrase id '{}'", phrase.id).into());
+            }
+            let reserved = RESERVED_PREFIXES
+                .iter()
+                .find(|prefix| phrase.id.starts_with(*prefix));
+            if let Some(prefix) = reserved {
+                return Err(format!(
+                    "phrase id '{}' starts with '{prefix}', which is kept for drills",
+                    phrase.id
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    // the nested lessons.json schema
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let lessons_wrapper = LessonsWrapperRef {
+            version: VERSION,
+            title: self.title.as_deref(),
+            source: self.source.as_deref(),
+            lessons: &self.lessons,
+        };
+        Ok(serde_json::to_string_pretty(&lessons_wrapper)?)
+    }
+
+    // problems with the vocab that would break or confuse a session
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::<Issue>::new();
+        if self.lessons.is_empty() {
+            issues.push(Issue::Error(String::from("no lessons")));
+        }
+        let mut lesson_indices = HashSet::<usize>::new();
+        for lesson in &self.lessons {
+            let lesson_name = format!("lesson {}", lesson.index);
+            if !lesson_indices.insert(lesson.index) {
+                issues.push(Issue::Error(format!(
+                    "{lesson_name}: duplicate lesson index"
+                )));
+            }
+            if lesson.sections.is_empty() {
+                issues.push(Issue::Error(format!("{lesson_name}: no sections")));
+            }
+            for section in &lesson.sections {
+                let section_name = format!("{lesson_name} '{}'", section.name);
+                if section.phrases.is_empty() {
+                    issues.push(Issue::Error(format!("{section_name}: no phrases")));
+                }
+                let mut phrases = HashSet::<(&str, &str)>::new();
+                for (phrase_idx, phrase) in section.phrases.iter().enumerate() {
+                    let phrase_name = format!("{section_name} phrase {phrase_idx}");
+                    if phrase.en.trim().is_empty() {
+                        issues.push(Issue::Error(format!("{phrase_name}: empty english")));
+                    }
+                    if phrase.jp.trim().is_empty() {
+                        issues.push(Issue::Error(format!("{phrase_name}: empty japanese")));
+                    }
+                    if phrase
+                        .kanji
+                        .as_ref()
+                        .is_some_and(|kanji| kanji.trim().is_empty())
+                    {
+                        issues.push(Issue::Error(format!("{phrase_name}: empty kanji")));
+                    }
+                    if !phrases.insert((&phrase.en, &phrase.jp)) {
+                        issues.push(Issue::Warning(format!(
+                            "{phrase_name}: duplicate of '{}' - '{}'",
+                            phrase.en, phrase.jp
+                        )));
+                    }
+                }
+            }
+        }
+        issues
+    }
+}
+
+// fnv-1a of the english and japanese, the same on every run and platform
+fn phrase_hash(phrase: &Phrase) -> String {
+    let text = format!("{}\u{1f}{}", phrase.en, phrase.jp);
+    let hash = text.bytes().fold(0x811c9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    });
+    format!("{hash:08x}")
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    Error(String),
+    Warning(String),
 }
 
 impl Index<usize> for Book {
@@ -31,11 +203,27 @@ impl Index<usize> for Book {
 
 #[derive(Debug, Deserialize)]
 struct LessonsWrapper {
+    #[serde(default)]
+    version: Option<u32>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    source: Option<String>,
     #[serde(default)]
     lessons: Vec<Lesson>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize)]
+struct LessonsWrapperRef<'a> {
+    version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    lessons: &'a [Lesson],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Lesson {
     pub index: usize,
     pub name_en: String,
@@ -44,16 +232,229 @@ pub struct Lesson {
     pub sections: Vec<Section>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Section {
     pub name: String,
     #[serde(default)]
     pub phrases: Vec<Phrase>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Phrase {
+    // stable across edits of the lessons, assigned on load when missing
+    #[serde(default)]
+    pub id: String,
+    // ids the phrase had before, their progress is moved to the id on load
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub former_ids: Vec<String>,
     pub en: String,
     pub jp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub kanji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part_of_speech: Option<PartOfSpeech>,
+    // usage notes, e.g. which particle goes with a verb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Example>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PartOfSpeech {
+    UVerb,
+    RuVerb,
+    IrregularVerb,
+    #[serde(alias = "い-adj")]
+    IAdj,
+    #[serde(alias = "な-adj")]
+    NaAdj,
+    Noun,
+    Expression,
+}
+
+impl PartOfSpeech {
+    pub fn label(self) -> &'static str {
+        match self {
+            PartOfSpeech::UVerb => "u-verb",
+            PartOfSpeech::RuVerb => "ru-verb",
+            PartOfSpeech::IrregularVerb => "irr. verb",
+            PartOfSpeech::IAdj => "い-adj",
+            PartOfSpeech::NaAdj => "な-adj",
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Expression => "expression",
+        }
+    }
+}
+
+// a sentence using the phrase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Example {
+    pub jp: String,
+    #[serde(default)]
+    pub en: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        // version 1 files have no version and no ids
+        let book = Book::from_json(
+            r#"{"lessons": [{"index": 3, "name_en": "Weekend", "name_jp": "しゅうまつ", "sections": [
+                {"name": "Places", "phrases": [{"en": "school", "jp": "がっこう"}]},
+                {"name": "Verbs", "phrases": [{"en": "to go", "jp": "いく"}, {"en": "to wait", "jp": "まつ"}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let ids: Vec<&str> = book.lessons[0]
+            .sections
+            .iter()
+            .flat_map(|section| &section.phrases)
+            .map(|phrase| phrase.id.as_str())
+            .collect();
+        // the same keys progress was saved under
+        assert_eq!(ids, vec!["3/0/0", "3/1/0", "3/1/1"]);
+        assert_eq!(ids[2], Progress::key(3, 1, 1));
+
+        let json = book.to_json().unwrap();
+        assert!(json.starts_with("{\n  \"version\": 2,\n  \"lessons\""));
+        assert_eq!(Book::from_json(&json).unwrap().lessons, book.lessons);
+
+        // ids stay with their phrase when phrases are inserted or moved
+        let book = Book::from_json(
+            r#"{"version": 2, "source": "Genki I", "lessons": [{"index": 3, "name_en": "Weekend", "name_jp": "しゅうまつ", "sections": [
+                {"name": "Verbs", "phrases": [{"en": "to come", "jp": "くる"}, {"id": "3/1/1", "en": "to wait", "jp": "まつ"}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let phrases = &book.lessons[0].sections[0].phrases;
+        assert_eq!(
+            (phrases[0].id.as_str(), phrases[1].id.as_str()),
+            (phrase_hash(&phrases[0]).as_str(), "3/1/1")
+        );
+        assert_eq!(book.source.as_deref(), Some("Genki I"));
+
+        // a phrase inserted without an id doesn't take the id of the phrase
+        // that was there before, nor a made up one that is already used
+        let inserted = Phrase {
+            en: String::from("to come"),
+            jp: String::from("くる"),
+            ..Phrase::default()
+        };
+        let json = format!(
+            r#"{{"version": 2, "lessons": [{{"index": 3, "name_en": "Weekend", "name_jp": "しゅうまつ", "sections": [
+                {{"name": "Verbs", "phrases": [{{"en": "to come", "jp": "くる"}}, {{"id": "3/0/0", "en": "to go", "jp": "いく"}}, {{"id": "{}", "en": "to wait", "jp": "まつ"}}]}}
+            ]}}]}}"#,
+            phrase_hash(&inserted)
+        );
+        let book = Book::from_json(&json).unwrap();
+        let ids: Vec<String> = book.lessons[0].sections[0]
+            .phrases
+            .iter()
+            .map(|phrase| phrase.id.clone())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                format!("{}-2", phrase_hash(&inserted)),
+                String::from("3/0/0"),
+                phrase_hash(&inserted),
+            ]
+        );
+
+        let test_cases = vec![
+            (
+                r#"{"version": 3, "lessons": []}"#,
+                "version 3 needs a newer keyboard",
+            ),
+            (r#"{"version": "two"}"#, "invalid type"),
+            (
+                r#"{"version": 2, "lessons": [{"index": 1, "name_en": "New Friends", "name_jp": "ともだち", "sections": [
+                    {"name": "School", "phrases": [{"id": "a", "en": "student", "jp": "がくせい"}, {"id": "a", "en": "teacher", "jp": "せんせい"}]}
+                ]}]}"#,
+                "duplicate phrase id 'a'",
+            ),
+        ];
+        for (json, expected) in test_cases {
+            let err = Book::from_json(json).err().unwrap().to_string();
+            assert!(err.contains(expected), "Failed for json: {}", json);
+        }
+    }
+
+    #[test]
+    fn test_phrase_fields() {
+        let test_cases = vec![
+            (r#""u-verb""#, PartOfSpeech::UVerb),
+            (r#""irregular-verb""#, PartOfSpeech::IrregularVerb),
+            (r#""i-adj""#, PartOfSpeech::IAdj),
+            (r#""い-adj""#, PartOfSpeech::IAdj),
+            (r#""な-adj""#, PartOfSpeech::NaAdj),
+            (r#""expression""#, PartOfSpeech::Expression),
+        ];
+        for (json, expected) in test_cases {
+            let json = format!(r#"{{"en": "to wait", "jp": "まつ", "part_of_speech": {json}}}"#);
+            let phrase: Phrase = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                phrase.part_of_speech,
+                Some(expected),
+                "Failed for json: {}",
+                json
+            );
+        }
+
+        // every field is optional and left out when empty
+        let phrase: Phrase = serde_json::from_str(
+            r#"{"en": "to wait", "jp": "まつ", "notes": "~を", "examples": [{"jp": "まって!"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(phrase.part_of_speech, None);
+        assert_eq!(phrase.examples[0].en, "");
+        assert_eq!(
+            serde_json::to_string(&phrase).unwrap(),
+            r#"{"id":"","en":"to wait","jp":"まつ","notes":"~を","examples":[{"jp":"まって!","en":""}]}"#
+        );
+        assert!(
+            serde_json::from_str::<Phrase>(
+                r#"{"en": "a", "jp": "あ", "part_of_speech": "adverb"}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_ids() {
+        let mut book = Book::from_json(
+            r#"{"version": 2, "lessons": [{"index": 1, "name_en": "New Friends", "name_jp": "ともだち", "sections": [
+                {"name": "School", "phrases": [{"id": "a", "en": "student", "jp": "がくせい"}]},
+                {"name": "Person", "phrases": [{"id": "b", "en": "teacher", "jp": "せんせい"}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert!(book.check_ids().is_ok());
+        // in different sections too
+        book.lessons[0].sections[1].phrases[0].id = String::from("a");
+        assert_eq!(
+            book.check_ids().err().unwrap().to_string(),
+            "duplicate phrase id 'a'"
+        );
+        // drill keys would merge with the progress of the drills
+        for id in RESERVED_PREFIXES.map(|prefix| format!("{prefix}a")) {
+            book.lessons[0].sections[1].phrases[0].id = id.clone();
+            assert!(
+                book.check_ids()
+                    .err()
+                    .unwrap()
+                    .to_string()
+                    .contains("kept for drills"),
+                "Failed for id: {}",
+                id
+            );
+        }
+    }
 }
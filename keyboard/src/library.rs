@@ -0,0 +1,215 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lessons::{Book, Phrase};
+use crate::progress::Progress;
+use crate::resources::Resources;
+use crate::tabular::Delimiter;
+
+// one book on the book picker
+pub struct Shelf {
+    pub name: String,
+    pub path: PathBuf,
+    // progress of decks is kept apart from the lessons file, whose keys
+    // stay unprefixed so older progress still matches
+    pub progress_prefix: Option<String>,
+    pub book: Book,
+}
+
+impl Shelf {
+    pub fn new(book: Book, path: &Path, progress_prefix: Option<String>) -> Self {
+        let name = book.title.clone().unwrap_or_else(|| file_stem(path));
+        Shelf {
+            name,
+            path: path.to_path_buf(),
+            progress_prefix,
+            book,
+        }
+    }
+
+    pub fn phrase_count(&self) -> usize {
+        self.phrases().count()
+    }
+
+    pub fn phrases(&self) -> impl Iterator<Item = &Phrase> {
+        self.book
+            .lessons
+            .iter()
+            .flat_map(|lesson| &lesson.sections)
+            .flat_map(|section| &section.phrases)
+    }
+
+    // progress store key of a phrase of this book
+    pub fn progress_key(&self, phrase: &Phrase) -> String {
+        self.key(&phrase.id)
+    }
+
+    // progress store key of a phrase id
+    pub fn key(&self, id: &str) -> String {
+        match &self.progress_prefix {
+            Some(prefix) => format!("{prefix}:{id}"),
+            None => String::from(id),
+        }
+    }
+
+    // moves progress saved under the former ids of phrases to their id
+    pub fn migrate_progress(&self, progress: &mut Progress) {
+        for phrase in self.phrases() {
+            for former_id in &phrase.former_ids {
+                progress.move_key(&self.key(former_id), self.progress_key(phrase));
+            }
+        }
+    }
+}
+
+// the lessons file followed by every book in the decks directory
+pub struct Library {
+    pub shelves: Vec<Shelf>,
+    // decks that couldnt be loaded, the rest of the library still works
+    pub warnings: Vec<String>,
+}
+
+impl Library {
+    // a broken lessons file is an error, a broken deck only a warning
+    pub fn load(resources: &Resources) -> Result<Self, Box<dyn Error>> {
+        let lessons_path = resources.lessons_path();
+        let mut library = Library::new(Book::from_file(&lessons_path)?, &lessons_path);
+
+        // no decks directory just means no extra books
+        let Ok(entries) = fs::read_dir(resources.decks_path()) else {
+            return Ok(library);
+        };
+        let skipped = [lessons_path, resources.kana_path(), resources.kanji_path()];
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_deck(path) && !skipped.contains(path))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match Book::from_file(&path) {
+                Ok(book) if book.lessons.is_empty() => library
+                    .warnings
+                    .push(format!("{}: no lessons", path.display())),
+                Ok(book) => library.add(book, &path),
+                Err(err) => library.warnings.push(err.to_string()),
+            }
+        }
+        Ok(library)
+    }
+
+    pub fn new(book: Book, path: &Path) -> Self {
+        Library {
+            shelves: vec![Shelf::new(book, path, None)],
+            warnings: Vec::new(),
+        }
+    }
+
+    // a deck, its progress is kept under its file name. the extension stays
+    // so class.csv and class.json don't share progress
+    pub fn add(&mut self, book: Book, path: &Path) {
+        let prefix = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.shelves.push(Shelf::new(book, path, Some(prefix)));
+    }
+}
+
+fn is_deck(path: &Path) -> bool {
+    let json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    path.is_file() && (json || Delimiter::from_path(path).is_some())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::env;
+
+    // a deck with the given phrases in its one section
+    pub fn deck_json(phrases: &str) -> String {
+        format!(
+            r#"{{"version": 2, "title": "JLPT N5", "lessons": [{{"index": 1, "name_en": "Nouns", "name_jp": "めいし",
+                "sections": [{{"name": "People", "phrases": {phrases}}}]}}]}}"#
+        )
+    }
+
+    // adds it as decks/jlpt-n5.json, its progress is kept under
+    // "jlpt-n5.json:"
+    pub fn add_deck(library: &mut Library, phrases: &str) {
+        let book = Book::from_json(&deck_json(phrases)).unwrap();
+        library.add(book, Path::new("decks/jlpt-n5.json"));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = env::temp_dir().join(format!("keyboard-library-{}", std::process::id()));
+        let decks = dir.join("decks");
+        fs::create_dir_all(&decks).unwrap();
+        for name in ["lessons.json", "kana.json", "kanji.json"] {
+            fs::copy(Path::new("resources").join(name), dir.join(name)).unwrap();
+        }
+        let deck = deck_json(r#"[{"en": "person", "jp": "ひと"}]"#);
+        let files = [
+            ("jlpt-n5.json", deck.as_str()),
+            (
+                "class.csv",
+                "lesson,section,en,jp\n1,Week 1,teacher,せんせい\n1,Week 1,student,がくせい\n",
+            ),
+            // same name as the csv, its progress is kept apart
+            (
+                "class.json",
+                r#"{"title": "Class notes", "lessons": [{"index": 1, "name_en": "Week 1", "name_jp": "いっしゅう",
+                    "sections": [{"name": "Week 1", "phrases": [{"en": "teacher", "jp": "せんせい"}]}]}]}"#,
+            ),
+            ("broken.json", "{"),
+            ("empty.json", "{}"),
+            ("notes.txt", "not a deck"),
+        ];
+        for (name, contents) in files {
+            fs::write(decks.join(name), contents).unwrap();
+        }
+
+        let library = Library::load(&Resources::new(dir.clone())).unwrap();
+        let test_cases = vec![
+            ("Genki I", None, 720),
+            ("class", Some("class.csv"), 2),
+            ("Class notes", Some("class.json"), 1),
+            ("JLPT N5", Some("jlpt-n5.json"), 1),
+        ];
+        assert_eq!(library.shelves.len(), test_cases.len());
+        for (shelf, (name, prefix, phrase_count)) in library.shelves.iter().zip(test_cases) {
+            assert_eq!(shelf.name, name, "Failed for book: {}", name);
+            assert_eq!(
+                shelf.progress_prefix.as_deref(),
+                prefix,
+                "Failed for book: {}",
+                name
+            );
+            assert_eq!(
+                shelf.phrase_count(),
+                phrase_count,
+                "Failed for book: {}",
+                name
+            );
+        }
+        assert_eq!(library.warnings.len(), 2);
+        assert!(library.warnings[0].contains("broken.json"));
+        assert!(library.warnings[1].contains("empty.json: no lessons"));
+
+        // the decks directory is optional
+        fs::remove_dir_all(&decks).unwrap();
+        let library = Library::load(&Resources::new(dir.clone())).unwrap();
+        assert_eq!(library.shelves.len(), 1);
+        assert!(library.warnings.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,38 +1,117 @@
+mod anki;
 mod app;
+mod cli;
+mod composer;
+mod config;
+mod furigana;
+mod grading;
 mod kana_converter;
+mod kana_drill;
 mod kanji_converter;
+mod keymap;
 mod lessons;
+mod library;
+mod preferences;
+mod progress;
+mod resources;
+mod server;
+mod tabular;
+mod theme;
 mod ui;
 
-use rand::Rng;
-use std::{collections::HashSet, error::Error, io};
+use clap::Parser;
+use std::{error::Error, io, process::ExitCode};
 
 use ratatui::{
     Terminal,
     backend::{Backend, CrosstermBackend},
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+        event::{
+            self, DisableMouseCapture, EnableMouseCapture, Event, KeyboardEnhancementFlags,
+            PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
         execute,
-        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+        terminal::{
+            EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+            supports_keyboard_enhancement,
+        },
     },
 };
 
-use app::{App, CurrentScreen, CurrentSelection, TranslationDirection};
+use app::{App, Transition};
+use cli::{Cli, Command, PracticeArgs};
+use config::Config;
+use preferences::Preferences;
+use progress::Progress;
+use resources::Resources;
 use ui::ui;
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let cli = Cli::parse();
+    // a broken config is reported before the terminal is taken over
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("error: {error}");
+            }
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let resources = config.resources(cli.resources.clone());
+    match &cli.command {
+        Some(Command::Convert(args)) => cli::convert(&resources, args),
+        Some(Command::Validate) => cli::validate(&resources),
+        Some(Command::Stats) => cli::stats(&resources),
+        Some(Command::Serve(args)) => server::serve(&resources, args),
+        Some(Command::Export(args)) => cli::export(&resources, args),
+        Some(Command::Import(args)) => cli::import(args),
+        Some(Command::Anki(args)) => cli::anki(&resources, args),
+        Some(Command::Practice(args)) => practice(&resources, &config, cli.seed, Some(args)),
+        None => practice(&resources, &config, cli.seed, None),
+    }
+}
+
+fn practice(
+    resources: &Resources,
+    config: &Config,
+    seed: Option<u64>,
+    args: Option<&PracticeArgs>,
+) -> Result<ExitCode, Box<dyn Error>> {
+    // load everything before touching the terminal so errors stay readable
+    let mut app = App::new(resources, Preferences::load(), seed)?;
+    app.progress = Progress::load();
+    app.migrate_progress();
+    config.apply(&mut app);
+    if let Some(args) = args {
+        cli::apply_practice(&mut app, args)?;
+    }
+
     // setup terminal
     enable_raw_mode()?;
     let mut stderr = io::stderr(); // This is a special case. Normally using stdout is fine
     execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
+    // report modifiers and key releases on terminals that support it
+    let keyboard_enhancement = supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhancement {
+        execute!(
+            stderr,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+    }
     let backend = CrosstermBackend::new(stderr);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let mut app = App::new();
+    // run it
     let res = run_app(&mut terminal, &mut app);
 
     // restore terminal
+    if keyboard_enhancement {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -40,6 +119,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    // printed before the terminal was taken over they'd be wiped at once
+    for warning in &app.library.warnings {
+        eprintln!("warning: {warning}");
+    }
 
     if let Ok(res) = res {
         if res {
@@ -49,7 +132,14 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("{err:?}");
     }
 
-    Ok(())
+    if let Err(err) = app.preferences.save() {
+        println!("couldnt save preferences: {err}");
+    }
+    if let Err(err) = app.progress.save() {
+        println!("couldnt save progress: {err}");
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
 
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<bool>
@@ -59,304 +149,12 @@ where
     loop {
         terminal.draw(|f| ui(f, app))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Release {
-                // Skip events that are not KeyEventKind::Press
-                break;
-            }
-            match app.context.current_screen {
-                CurrentScreen::Welcome => match key.code {
-                    KeyCode::Esc => {
-                        break;
-                    }
-                    _ => {
-                        app.context.current_screen = CurrentScreen::LessonSelect;
-                    }
-                },
-                CurrentScreen::LessonSelect => match app.context.current_selection {
-                    CurrentSelection::Lesson => match key.code {
-                        KeyCode::Esc => {
-                            break;
-                        }
-                        KeyCode::Enter => {
-                            app.context.current_screen = CurrentScreen::Review;
-                            let translation_direction = rand::thread_rng().gen_range(0..=1);
-                            if translation_direction == 0 {
-                                app.context.translation_direction = TranslationDirection::ToJP;
-                            } else {
-                                app.context.translation_direction = TranslationDirection::ToEN;
-                            }
-                            assert!(app.context.lesson_idx < app.book.lessons.len());
-                            let lesson = &app.book.lessons[app.context.lesson_idx];
-                            app.context.section_idx =
-                                Some(rand::thread_rng().gen_range(0..lesson.sections.len()));
-                            let section = &lesson.sections[app.context.section_idx.unwrap()];
-                            app.context.phrase_idx =
-                                rand::thread_rng().gen_range(0..section.phrases.len());
-                            app.context.randomize_section = true;
-                            app.context.asked_questions.clear();
-                            app.context.asked_questions =
-                                std::iter::repeat_with(|| HashSet::<usize>::new())
-                                    .take(lesson.sections.len())
-                                    .collect();
-                            assert!(app.context.asked_questions.len() == lesson.sections.len());
-                        }
-                        KeyCode::Down => {
-                            if app.context.lesson_idx + 1 >= app.book.lessons.len() {
-                                app.context.lesson_idx = 0;
-                            } else {
-                                app.context.lesson_idx += 1;
-                            }
-                        }
-                        KeyCode::Up => {
-                            if app.context.lesson_idx == 0 {
-                                app.context.lesson_idx = app.book.lessons.len() - 1;
-                            } else {
-                                app.context.lesson_idx -= 1;
-                            }
-                        }
-                        KeyCode::Right => {
-                            app.context.current_selection = CurrentSelection::Section;
-                            app.context.section_idx = Some(0);
-                            app.context.randomize_section = false;
-                        }
-                        _ => {}
-                    },
-                    CurrentSelection::Section => match key.code {
-                        KeyCode::Esc => {
-                            break;
-                        }
-                        KeyCode::Enter => {
-                            app.context.current_screen = CurrentScreen::Review;
-                            let translation_direction = rand::thread_rng().gen_range(0..=1);
-                            if translation_direction == 0 {
-                                app.context.translation_direction = TranslationDirection::ToJP;
-                            } else {
-                                app.context.translation_direction = TranslationDirection::ToEN;
-                            }
-                            assert!(app.context.lesson_idx < app.book.lessons.len());
-                            let lesson = &app.book.lessons[app.context.lesson_idx];
-                            assert!(
-                                app.context.section_idx.expect("section index not set")
-                                    < lesson.sections.len()
-                            );
-                            let section = &lesson.sections[app.context.section_idx.unwrap()];
-                            app.context.phrase_idx =
-                                rand::thread_rng().gen_range(0..section.phrases.len());
-
-                            app.context.randomize_section = false;
-                            app.context.asked_questions.clear();
-                            app.context.asked_questions =
-                                std::iter::repeat_with(|| HashSet::<usize>::new())
-                                    .take(lesson.sections.len())
-                                    .collect();
-                            assert!(app.context.asked_questions.len() == lesson.sections.len());
-                        }
-                        KeyCode::Down => {
-                            assert!(app.context.lesson_idx < app.book.lessons.len());
-                            let lesson = &app.book.lessons[app.context.lesson_idx];
-                            if app.context.section_idx.expect("section index not set") + 1
-                                >= lesson.sections.len()
-                            {
-                                app.context.section_idx = Some(0);
-                            } else {
-                                app.context.section_idx =
-                                    Some(app.context.section_idx.unwrap() + 1);
-                            }
-                        }
-                        KeyCode::Up => {
-                            assert!(app.context.lesson_idx < app.book.lessons.len());
-                            let lesson = &app.book.lessons[app.context.lesson_idx];
-                            if app.context.section_idx.expect("section index not set") == 0 {
-                                app.context.section_idx = Some(lesson.sections.len() - 1);
-                            } else {
-                                app.context.section_idx =
-                                    Some(app.context.section_idx.unwrap() - 1);
-                            }
-                        }
-                        KeyCode::Left => {
-                            app.context.current_selection = CurrentSelection::Lesson;
-                            app.context.section_idx = None;
-                        }
-                        _ => {}
-                    },
-                },
-                CurrentScreen::Review => match key.code {
-                    KeyCode::Esc => {
-                        app.context.current_screen = CurrentScreen::LessonSelect;
-                        app.context.current_selection = CurrentSelection::Lesson;
-                        app.context.lesson_idx = 0;
-                        app.context.section_idx = None;
-                        app.context.prev_section_idx = None;
-                        app.context.prev_phrase_idx = None;
-                        app.context.prev_translation_direction = None;
-                        app.context.prev_answer = None;
-                        app.context.asked_questions.clear();
-                        app.context.kanji_offset = 0;
-                        app.romanji.clear();
-                        app.kana.clear();
-                        app.kanji.clear();
-                        app.highlighted_kanji.clear();
-                        app.kana_offset = 0;
-                        app.kana_len = 1;
-                    }
-                    KeyCode::Enter => {
-                        app.context.prev_section_idx = app.context.section_idx;
-                        app.context.prev_phrase_idx = Some(app.context.phrase_idx);
-                        app.context.prev_translation_direction =
-                            Some(app.context.translation_direction);
-                        if let Some(prev_translation_direction) =
-                            app.context.prev_translation_direction
-                        {
-                            match prev_translation_direction {
-                                TranslationDirection::ToEN => {
-                                    app.context.prev_answer = Some(app.romanji.clone());
-                                }
-                                TranslationDirection::ToJP => {
-                                    app.context.prev_answer = Some(app.kanji.clone());
-                                }
-                            }
-                        }
-                        let translation_direction = rand::thread_rng().gen_range(0..=1);
-                        if translation_direction == 0 {
-                            app.context.translation_direction = TranslationDirection::ToJP;
-                        } else {
-                            app.context.translation_direction = TranslationDirection::ToEN;
-                        }
-                        assert!(app.context.lesson_idx < app.book.lessons.len());
-                        let lesson = &app.book.lessons[app.context.lesson_idx];
-                        if app.context.randomize_section == true {
-                            let mut asked_sections = HashSet::<usize>::new();
-                            loop {
-                                let section_idx =
-                                    rand::thread_rng().gen_range(0..lesson.sections.len());
-                                assert!(section_idx < app.context.asked_questions.len());
-                                if app.context.asked_questions[section_idx].len()
-                                    == lesson.sections[section_idx].phrases.len()
-                                {
-                                    asked_sections.insert(section_idx);
-                                    if asked_sections.len() == lesson.sections.len() {
-                                        app.context.asked_questions.clear();
-                                        app.context.asked_questions =
-                                            std::iter::repeat_with(|| HashSet::<usize>::new())
-                                                .take(lesson.sections.len())
-                                                .collect();
-                                    }
-                                } else {
-                                    app.context.section_idx = Some(section_idx);
-                                    break;
-                                }
-                            }
-                        }
-                        assert!(
-                            app.context.section_idx.expect("section index not set")
-                                < lesson.sections.len()
-                        );
-                        assert!(
-                            app.context.section_idx.unwrap() < app.context.asked_questions.len()
-                        );
-                        let section = &lesson.sections[app.context.section_idx.unwrap()];
-                        loop {
-                            let phrases_asked =
-                                &mut app.context.asked_questions[app.context.section_idx.unwrap()];
-                            let phrase_idx = rand::thread_rng().gen_range(0..section.phrases.len());
-                            if phrases_asked.insert(phrase_idx) == true {
-                                app.context.phrase_idx = phrase_idx;
-                                if app.context.randomize_section == false
-                                    && phrases_asked.len() == section.phrases.len()
-                                {
-                                    phrases_asked.clear();
-                                }
-                                break;
-                            }
-                        }
-                        app.romanji.clear();
-                        app.kana.clear();
-                        app.kanji.clear();
-                        app.highlighted_kanji.clear();
-                        app.kana_offset = 0;
-                        app.kana_len = 1;
-                    }
-                    KeyCode::Tab => {
-                        if app.get_kana().chars().count() > 0 {
-                            assert!(
-                                app.kana_offset < app.get_kana().chars().count()
-                                    && app.kana_offset + app.kana_len
-                                        <= app.get_kana().chars().count()
-                            );
-                            app.push_kanji_offset((
-                                app.kana_offset,
-                                app.kana_len,
-                                app.context.kanji_offset,
-                            ));
-                        }
-                    }
-                    KeyCode::Right => {
-                        if key.modifiers.contains(KeyModifiers::SHIFT) {
-                            if app.kana_offset + app.kana_len < app.get_kana().chars().count() {
-                                app.kana_len += 1;
-                            }
-                        } else {
-                            if app.kana_offset + app.kana_len < app.get_kana().chars().count() {
-                                app.kana_offset += 1;
-                            }
-                            app.kana_len = 1;
-                        }
-                        app.context.kanji_offset = 0;
-                    }
-                    KeyCode::Left => {
-                        if key.modifiers.contains(KeyModifiers::SHIFT) {
-                            if app.kana_len > 1 {
-                                app.kana_len -= 1;
-                            }
-                        } else {
-                            if app.kana_offset > 0 {
-                                app.kana_offset -= 1;
-                            }
-                            app.kana_len = 1;
-                        }
-                        app.context.kanji_offset = 0;
-                    }
-                    KeyCode::Up => {
-                        if app.context.kanji_offset > 0 {
-                            app.context.kanji_offset -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        if app.context.kanji_offset + 1 < app.highlighted_kanji.len() {
-                            app.context.kanji_offset += 1;
-                        }
-                    }
-                    KeyCode::Char(value) => {
-                        app.push_char(value);
-                        // when last charachter dissapears do to kana conversion
-                        if app.kana_offset + app.kana_len > app.get_kana().chars().count() {
-                            app.kana_offset -=
-                                app.kana_offset + app.kana_len - app.get_kana().chars().count();
-                        }
-                    }
-                    KeyCode::Backspace => {
-                        app.pop_char();
-                        // undo highlighted all chars if removing highlighted char
-                        if app.get_kana().chars().count() > 0
-                            && app.kana_len > 1
-                            && app.kana_offset + app.kana_len > app.get_kana().chars().count()
-                        {
-                            app.kana_len -= 1;
-                        }
-                        // move cursor back if cursor is at end of string
-                        if app.get_kana().chars().count() > 0
-                            && app.kana_offset + app.kana_len > app.get_kana().chars().count()
-                        {
-                            app.kana_offset -=
-                                app.kana_offset + app.kana_len - app.get_kana().chars().count();
-                        }
-                    }
-                    _ => {}
-                },
-            }
+        if let Event::Key(key) = event::read()?
+            && let Some(action) = app.keymap.action_for(app.context.current_screen, key)
+            && app.handle_event(action) == Transition::Quit
+        {
+            break;
         }
-        app.update_kanji();
     }
-    return Ok(true);
+    Ok(true)
 }
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::app::{DirectionMode, Drill};
+use crate::kana_drill::KanaPool;
+use crate::theme::ThemeName;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Preferences {
+    #[serde(default)]
+    pub direction_mode: DirectionMode,
+    #[serde(default)]
+    pub drill: Drill,
+    #[serde(default)]
+    pub kana_pools: Vec<KanaPool>,
+    #[serde(default)]
+    pub theme: ThemeName,
+    // only the kanji of a question, no readings above them
+    #[serde(default)]
+    pub hide_furigana: bool,
+}
+
+impl Preferences {
+    pub fn load() -> Self {
+        // missing or unreadable preferences fall back to defaults
+        let Some(path) = Preferences::path() else {
+            return Preferences::default();
+        };
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str::<Preferences>(&json).unwrap_or_default(),
+            Err(_) => Preferences::default(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Preferences::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("genki-keyboard").join("preferences.json"))
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// answers so far for one phrase
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhraseProgress {
+    #[serde(default)]
+    pub seen: u32,
+    #[serde(default)]
+    pub correct: u32,
+    // correct answers in a row, reset by a wrong one
+    #[serde(default)]
+    pub streak: u32,
+}
+
+impl PhraseProgress {
+    // · for unseen phrases, otherwise one dot per correct answer in a row
+    pub fn indicator(&self) -> String {
+        if self.seen == 0 {
+            return String::from("·");
+        }
+        let filled = self.streak.min(3) as usize;
+        format!("{}{}", "●".repeat(filled), "○".repeat(3 - filled))
+    }
+}
+
+// set by hand during review, at most one per phrase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mark {
+    // not asked anymore
+    Known,
+    // asked twice as often
+    Hard,
+    // something in the phrase is wrong and needs fixing in the lessons
+    BadData {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+}
+
+impl Mark {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mark::Known => "known",
+            Mark::Hard => "hard",
+            Mark::BadData { .. } => "flagged",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Mark::Known => "✓",
+            Mark::Hard => "!",
+            Mark::BadData { .. } => "⚑",
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    // keyed by phrase id, "<deck>:<id>" for phrases of decks
+    #[serde(default)]
+    pub phrases: BTreeMap<String, PhraseProgress>,
+    // same keys as phrases
+    #[serde(default)]
+    pub marks: BTreeMap<String, Mark>,
+}
+
+impl Progress {
+    // "<lesson index>/<section>/<phrase>", the key progress was saved under
+    // before phrases had ids and the id phrases without one get
+    pub fn key(lesson_index: usize, section_idx: usize, phrase_idx: usize) -> String {
+        format!("{lesson_index}/{section_idx}/{phrase_idx}")
+    }
+
+    pub fn get(&self, key: &str) -> PhraseProgress {
+        self.phrases.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn record(&mut self, key: String, correct: bool) {
+        let progress = self.phrases.entry(key).or_default();
+        progress.seen += 1;
+        if correct {
+            progress.correct += 1;
+            progress.streak += 1;
+        } else {
+            progress.streak = 0;
+        }
+    }
+
+    pub fn mark(&self, key: &str) -> Option<&Mark> {
+        self.marks.get(key)
+    }
+
+    pub fn is_marked(&self, key: &str, mark: &Mark) -> bool {
+        self.mark(key)
+            .is_some_and(|current| std::mem::discriminant(current) == std::mem::discriminant(mark))
+    }
+
+    // marking a phrase the same way again removes the mark, returns
+    // whether the phrase is marked afterwards
+    pub fn toggle_mark(&mut self, key: String, mark: Mark) -> bool {
+        if self.is_marked(&key, &mark) {
+            self.marks.remove(&key);
+            false
+        } else {
+            self.marks.insert(key, mark);
+            true
+        }
+    }
+
+    // progress of a phrase whose id changed, merged into what was already
+    // recorded under the new key. a mark under the new key wins
+    pub fn move_key(&mut self, from: &str, to: String) {
+        if from == to {
+            return;
+        }
+        if let Some(moved) = self.phrases.remove(from) {
+            let progress = self.phrases.entry(to.clone()).or_default();
+            if progress.seen == 0 {
+                progress.streak = moved.streak;
+            }
+            progress.seen += moved.seen;
+            progress.correct += moved.correct;
+        }
+        if let Some(mark) = self.marks.remove(from) {
+            self.marks.entry(to).or_insert(mark);
+        }
+    }
+
+    pub fn load() -> Self {
+        // missing or unreadable progress starts over
+        let Some(path) = Progress::path() else {
+            return Progress::default();
+        };
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str::<Progress>(&json).unwrap_or_default(),
+            Err(_) => Progress::default(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Progress::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("genki-keyboard").join("progress.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut progress = Progress::default();
+        let key = Progress::key(1, 2, 3);
+        assert_eq!(key, "1/2/3");
+        assert_eq!(progress.get(&key).indicator(), "·");
+
+        let test_cases = vec![
+            (true, (1, 1, 1), "●○○"),
+            (true, (2, 2, 2), "●●○"),
+            (false, (3, 2, 0), "○○○"),
+            (true, (4, 3, 1), "●○○"),
+            (true, (5, 4, 2), "●●○"),
+            (true, (6, 5, 3), "●●●"),
+            (true, (7, 6, 4), "●●●"),
+        ];
+        for (correct, (seen, total, streak), indicator) in test_cases {
+            progress.record(key.clone(), correct);
+            let result = progress.get(&key);
+            assert_eq!(
+                (result.seen, result.correct, result.streak),
+                (seen, total, streak),
+                "Failed for answer: {}",
+                seen
+            );
+            assert_eq!(result.indicator(), indicator, "Failed for answer: {}", seen);
+        }
+    }
+
+    #[test]
+    fn test_toggle_mark() {
+        let mut progress = Progress::default();
+        let key = Progress::key(0, 1, 2);
+        assert!(progress.toggle_mark(key.clone(), Mark::Known));
+        assert!(progress.is_marked(&key, &Mark::Known));
+        // another mark replaces the first
+        let bad_data = Mark::BadData {
+            note: Some(String::from("typo")),
+        };
+        assert!(progress.toggle_mark(key.clone(), bad_data.clone()));
+        assert_eq!(progress.mark(&key), Some(&bad_data));
+        assert!(!progress.toggle_mark(key.clone(), Mark::BadData { note: None }));
+        assert_eq!(progress.mark(&key), None);
+
+        progress.toggle_mark(key.clone(), bad_data);
+        let json = serde_json::to_string(&progress).unwrap();
+        assert_eq!(
+            json,
+            r#"{"phrases":{},"marks":{"0/1/2":{"bad-data":{"note":"typo"}}}}"#
+        );
+        // progress saved before marks existed still loads
+        let progress: Progress = serde_json::from_str(r#"{"phrases":{}}"#).unwrap();
+        assert!(progress.marks.is_empty());
+    }
+
+    #[test]
+    fn test_move_key() {
+        let mut progress = Progress::default();
+        progress.record(String::from("1/0/0"), true);
+        progress.record(String::from("1/0/0"), true);
+        progress.toggle_mark(String::from("1/0/0"), Mark::Hard);
+        progress.record(String::from("1/0/1"), false);
+        progress.toggle_mark(String::from("1/0/1"), Mark::Known);
+        progress.toggle_mark(String::from("school"), Mark::Known);
+
+        let test_cases = vec![
+            // nothing recorded under the new id yet
+            ("1/0/0", "student", (2, 2, 2), Some(Mark::Hard)),
+            // merged, the mark already there stays
+            ("1/0/1", "school", (1, 0, 0), Some(Mark::Known)),
+            // nothing to move
+            ("9/9/9", "house", (0, 0, 0), None),
+            ("student", "student", (2, 2, 2), Some(Mark::Hard)),
+        ];
+        for (from, to, (seen, correct, streak), mark) in test_cases {
+            progress.move_key(from, String::from(to));
+            let result = progress.get(to);
+            assert_eq!(
+                (result.seen, result.correct, result.streak),
+                (seen, correct, streak),
+                "Failed for key: {}",
+                from
+            );
+            assert_eq!(progress.mark(to), mark.as_ref(), "Failed for key: {}", from);
+            if from != to {
+                assert_eq!(progress.get(from).seen, 0, "Failed for key: {}", from);
+                assert_eq!(progress.mark(from), None, "Failed for key: {}", from);
+            }
+        }
+    }
+}
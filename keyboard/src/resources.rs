@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+// location of the lesson, kana and kanji json files
+#[derive(Debug, Clone)]
+pub struct Resources {
+    pub dir: PathBuf,
+    // files somewhere else than their default name in dir, relative to dir
+    pub lessons: Option<PathBuf>,
+    pub kana: Option<PathBuf>,
+    pub kanji: Option<PathBuf>,
+    // directory of extra books for the book picker
+    pub decks: Option<PathBuf>,
+}
+
+impl Default for Resources {
+    fn default() -> Self {
+        Resources::new(PathBuf::from("resources"))
+    }
+}
+
+impl Resources {
+    pub fn new(dir: PathBuf) -> Self {
+        Resources {
+            dir,
+            lessons: None,
+            kana: None,
+            kanji: None,
+            decks: None,
+        }
+    }
+
+    pub fn lessons_path(&self) -> PathBuf {
+        self.dir
+            .join(self.lessons.as_deref().unwrap_or("lessons.json".as_ref()))
+    }
+
+    pub fn kana_path(&self) -> PathBuf {
+        self.dir
+            .join(self.kana.as_deref().unwrap_or("kana.json".as_ref()))
+    }
+
+    pub fn kanji_path(&self) -> PathBuf {
+        self.dir
+            .join(self.kanji.as_deref().unwrap_or("kanji.json".as_ref()))
+    }
+
+    pub fn decks_path(&self) -> PathBuf {
+        self.dir
+            .join(self.decks.as_deref().unwrap_or("decks".as_ref()))
+    }
+}
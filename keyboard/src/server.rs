@@ -0,0 +1,426 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::ExitCode;
+
+use crate::cli::ServeArgs;
+use crate::composer::Composer;
+use crate::resources::Resources;
+
+// json-rpc 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    // requests without an id are notifications and get no response, an id
+    // of null still gets one
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+}
+
+// tells a null member apart from a missing one, which serde folds together
+fn present<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposeParams {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteParams {
+    #[serde(default = "default_count")]
+    count: usize,
+}
+
+impl Default for DeleteParams {
+    fn default() -> Self {
+        DeleteParams {
+            count: default_count(),
+        }
+    }
+}
+
+fn default_count() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct SpanParams {
+    offset: usize,
+    len: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SelectParams {
+    offset: usize,
+    len: usize,
+    candidate: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Preedit {
+    romaji: String,
+    kana: String,
+    text: String,
+    conversions: Vec<Conversion>,
+}
+
+#[derive(Debug, Serialize)]
+struct Conversion {
+    offset: usize,
+    len: usize,
+    candidate: usize,
+}
+
+// one composition per client, requests are handled in order
+pub struct Session {
+    composer: Composer,
+}
+
+impl Session {
+    pub fn new(composer: Composer) -> Self {
+        Session { composer }
+    }
+
+    // answers one line of json, None for notifications
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(line) {
+            Ok(value) => self.handle_value(value)?,
+            Err(err) => error_response(Value::Null, RpcError::new(PARSE_ERROR, err.to_string())),
+        };
+        Some(response.to_string())
+    }
+
+    fn handle_value(&mut self, value: Value) -> Option<Value> {
+        let request = match serde_json::from_value::<Request>(value) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(_) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+                ));
+            }
+            Err(err) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(INVALID_REQUEST, err.to_string()),
+                ));
+            }
+        };
+        let result = self.call(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(id, err),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "compose" => {
+                let params: ComposeParams = parse_params(params)?;
+                for value in params.text.chars() {
+                    self.composer.input(value);
+                }
+                Ok(self.preedit())
+            }
+            "delete" => {
+                let params: DeleteParams = if params.is_null() {
+                    DeleteParams::default()
+                } else {
+                    parse_params(params)?
+                };
+                for _ in 0..params.count {
+                    self.composer.delete_char();
+                }
+                Ok(self.preedit())
+            }
+            "preedit" => Ok(self.preedit()),
+            "candidates" => {
+                let params: SpanParams = parse_params(params)?;
+                let candidates = self
+                    .composer
+                    .candidates(params.offset, params.len)
+                    .ok_or_else(|| {
+                        RpcError::new(
+                            INVALID_PARAMS,
+                            format!("span {}+{} is outside the kana", params.offset, params.len),
+                        )
+                    })?;
+                let kana: String = self
+                    .composer
+                    .kana
+                    .chars()
+                    .skip(params.offset)
+                    .take(params.len)
+                    .collect();
+                let candidates: Vec<String> = candidates.iter().map(char::to_string).collect();
+                Ok(json!({ "kana": kana, "candidates": candidates }))
+            }
+            "select" => {
+                let params: SelectParams = parse_params(params)?;
+                self.composer
+                    .select(params.offset, params.len, params.candidate)
+                    .map_err(|err| RpcError::new(INVALID_PARAMS, err))?;
+                Ok(self.preedit())
+            }
+            "commit" => Ok(json!({ "text": self.composer.commit() })),
+            "reset" => {
+                self.composer.clear();
+                Ok(self.preedit())
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method named '{method}'"),
+            )),
+        }
+    }
+
+    fn preedit(&self) -> Value {
+        let preedit = Preedit {
+            romaji: self.composer.get_romanji(),
+            kana: self.composer.get_kana(),
+            text: self.composer.get_kanji(),
+            conversions: self
+                .composer
+                .conversions()
+                .iter()
+                .map(|&(offset, len, candidate)| Conversion {
+                    offset,
+                    len,
+                    candidate,
+                })
+                .collect(),
+        };
+        serde_json::to_value(preedit).expect("preedit always serializes")
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "error": err, "id": id })
+}
+
+// reads one request per line and writes one response per line
+fn serve_lines(
+    session: &mut Session,
+    reader: impl BufRead,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = session.handle_line(&line) {
+            writeln!(writer, "{response}")?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn serve(resources: &Resources, args: &ServeArgs) -> Result<ExitCode, Box<dyn Error>> {
+    match &args.socket {
+        Some(path) => serve_socket(resources, path),
+        None => {
+            let mut session = Session::new(Composer::new(resources)?);
+            serve_lines(&mut session, io::stdin().lock(), io::stdout().lock())?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_socket(resources: &Resources, path: &std::path::Path) -> Result<ExitCode, Box<dyn Error>> {
+    use std::os::unix::net::UnixListener;
+
+    // fail before listening if the resources are broken
+    Composer::new(resources)?;
+    let listener = UnixListener::bind(path)
+        .map_err(|err| format!("couldnt listen on {}: {err}", path.display()))?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let resources = resources.clone();
+        std::thread::spawn(move || {
+            let mut session = match Composer::new(&resources) {
+                Ok(composer) => Session::new(composer),
+                Err(err) => {
+                    eprintln!("couldnt start session: {err}");
+                    return;
+                }
+            };
+            let reader = match stream.try_clone() {
+                Ok(reader) => BufReader::new(reader),
+                Err(err) => {
+                    eprintln!("couldnt start session: {err}");
+                    return;
+                }
+            };
+            if let Err(err) = serve_lines(&mut session, reader, stream) {
+                eprintln!("session ended: {err}");
+            }
+        });
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(unix))]
+fn serve_socket(
+    _resources: &Resources,
+    _path: &std::path::Path,
+) -> Result<ExitCode, Box<dyn Error>> {
+    Err("unix sockets arent supported on this platform".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(session: &mut Session, request: Value) -> Value {
+        let response = session.handle_line(&request.to_string()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(Composer::new(&Resources::default()).unwrap());
+
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "compose", "params": { "text": "nihon" }, "id": 1 }),
+        );
+        assert_eq!(response["result"]["kana"], "にほん");
+        assert_eq!(response["id"], 1);
+
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "candidates", "params": { "offset": 0, "len": 1 }, "id": 2 }),
+        );
+        assert_eq!(
+            response["result"],
+            json!({ "kana": "に", "candidates": ["二", "日"] })
+        );
+
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "select", "params": { "offset": 0, "len": 1, "candidate": 1 }, "id": 3 }),
+        );
+        assert_eq!(response["result"]["text"], "日ほん");
+        assert_eq!(
+            response["result"]["conversions"],
+            json!([{ "offset": 0, "len": 1, "candidate": 1 }])
+        );
+
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "delete", "id": 4 }),
+        );
+        assert_eq!(response["result"]["romaji"], "niho");
+
+        // notifications get no response
+        let notification =
+            json!({ "jsonrpc": "2.0", "method": "compose", "params": { "text": "n" } });
+        assert_eq!(session.handle_line(&notification.to_string()), None);
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "preedit", "id": null }),
+        );
+        assert_eq!(response["result"]["romaji"], "nihon");
+        assert_eq!(response["id"], Value::Null);
+
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "commit", "id": 5 }),
+        );
+        assert_eq!(response["result"], json!({ "text": "日ほん" }));
+        let response = request(
+            &mut session,
+            json!({ "jsonrpc": "2.0", "method": "preedit", "id": 6 }),
+        );
+        assert_eq!(response["result"]["romaji"], "");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut session = Session::new(Composer::new(&Resources::default()).unwrap());
+        let test_cases = vec![
+            ("{", PARSE_ERROR),
+            (r#"{"method": "preedit", "id": 1}"#, INVALID_REQUEST),
+            (
+                r#"{"jsonrpc": "1.0", "method": "preedit", "id": 1}"#,
+                INVALID_REQUEST,
+            ),
+            (
+                r#"{"jsonrpc": "2.0", "method": "unknown", "id": 1}"#,
+                METHOD_NOT_FOUND,
+            ),
+            (
+                r#"{"jsonrpc": "2.0", "method": "compose", "id": 1}"#,
+                INVALID_PARAMS,
+            ),
+            (
+                r#"{"jsonrpc": "2.0", "method": "candidates", "params": {"offset": 3, "len": 1}, "id": 1}"#,
+                INVALID_PARAMS,
+            ),
+            (
+                r#"{"jsonrpc": "2.0", "method": "select", "params": {"offset": 0, "len": 1, "candidate": 0}, "id": 1}"#,
+                INVALID_PARAMS,
+            ),
+        ];
+        for (line, code) in test_cases {
+            let response: Value =
+                serde_json::from_str(&session.handle_line(line).unwrap()).unwrap();
+            assert_eq!(response["error"]["code"], code, "Failed for line: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_serve_lines() {
+        let mut session = Session::new(Composer::new(&Resources::default()).unwrap());
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "method": "compose", "params": {"text": "ka"}}"#,
+            "\n\n",
+            r#"{"jsonrpc": "2.0", "method": "commit", "id": "a"}"#,
+            "\n",
+        );
+        let mut output = Vec::<u8>::new();
+        serve_lines(&mut session, input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"id\":\"a\",\"jsonrpc\":\"2.0\",\"result\":{\"text\":\"か\"}}\n"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::lessons::{self, Book, Example, Lesson, PartOfSpeech, Phrase, Section};
+
+// field separator of a flat vocabulary file
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    // .csv and .tsv files, None for anything else
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Delimiter::Comma),
+            "tsv" => Some(Delimiter::Tab),
+            _ => None,
+        }
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            Delimiter::Comma => b',',
+            Delimiter::Tab => b'\t',
+        }
+    }
+}
+
+// one phrase per row, the lesson names and the book title and source are
+// repeated on every row so a spreadsheet can be sorted or filtered without
+// losing them. lessons and sections without phrases get a row with no en
+// and jp, and no section name for a lesson without sections
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Row {
+    // empty for new phrases, they get an id when imported
+    #[serde(default)]
+    id: String,
+    lesson: usize,
+    #[serde(default)]
+    lesson_en: String,
+    #[serde(default)]
+    lesson_jp: String,
+    section: String,
+    en: String,
+    jp: String,
+    // empty when the phrase has no kanji
+    #[serde(default)]
+    kanji: String,
+    #[serde(default)]
+    part_of_speech: Option<PartOfSpeech>,
+    #[serde(default)]
+    notes: String,
+    // one per line, japanese and english separated by " | "
+    #[serde(default)]
+    examples: String,
+    // separated by semicolons
+    #[serde(default)]
+    tags: String,
+    // separated by semicolons like the tags
+    #[serde(default)]
+    former_ids: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    source: String,
+}
+
+const TAG_SEPARATOR: &str = "; ";
+const EXAMPLE_SEPARATOR: &str = " | ";
+
+pub fn export(book: &Book, writer: impl Write, delimiter: Delimiter) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter.byte())
+        .from_writer(writer);
+    for lesson in &book.lessons {
+        let lesson_row = |section: &str| Row {
+            lesson: lesson.index,
+            lesson_en: lesson.name_en.clone(),
+            lesson_jp: lesson.name_jp.clone(),
+            section: String::from(section),
+            title: book.title.clone().unwrap_or_default(),
+            source: book.source.clone().unwrap_or_default(),
+            ..Row::default()
+        };
+        if lesson.sections.is_empty() {
+            writer.serialize(lesson_row(""))?;
+        }
+        for section in &lesson.sections {
+            if section.phrases.is_empty() {
+                writer.serialize(lesson_row(&section.name))?;
+            }
+            for phrase in &section.phrases {
+                writer.serialize(Row {
+                    id: phrase.id.clone(),
+                    en: phrase.en.clone(),
+                    jp: phrase.jp.clone(),
+                    kanji: phrase.kanji.clone().unwrap_or_default(),
+                    part_of_speech: phrase.part_of_speech,
+                    notes: phrase.notes.clone().unwrap_or_default(),
+                    examples: phrase
+                        .examples
+                        .iter()
+                        .map(|example| format!("{}{EXAMPLE_SEPARATOR}{}", example.jp, example.en))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                    tags: phrase.tags.join(TAG_SEPARATOR),
+                    former_ids: phrase.former_ids.join(TAG_SEPARATOR),
+                    ..lesson_row(&section.name)
+                })?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// rows are grouped into lessons and sections in the order they first
+// appear, the lesson names, title and source come from the first row that
+// has them
+pub fn import(reader: impl Read, delimiter: Delimiter) -> Result<Book, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter.byte())
+        .from_reader(reader);
+    let mut book = Book {
+        title: None,
+        source: None,
+        lessons: Vec::new(),
+    };
+    for row in reader.deserialize::<Row>() {
+        let row = row?;
+        if book.title.is_none() && !row.title.is_empty() {
+            book.title = Some(row.title);
+        }
+        if book.source.is_none() && !row.source.is_empty() {
+            book.source = Some(row.source);
+        }
+        let lessons = &mut book.lessons;
+        let lesson_idx = match lessons.iter().position(|lesson| lesson.index == row.lesson) {
+            Some(lesson_idx) => lesson_idx,
+            None => {
+                lessons.push(Lesson {
+                    index: row.lesson,
+                    name_en: String::new(),
+                    name_jp: String::new(),
+                    sections: Vec::new(),
+                });
+                lessons.len() - 1
+            }
+        };
+        let lesson = &mut lessons[lesson_idx];
+        if lesson.name_en.is_empty() {
+            lesson.name_en = row.lesson_en;
+        }
+        if lesson.name_jp.is_empty() {
+            lesson.name_jp = row.lesson_jp;
+        }
+        // a lesson without sections
+        let is_phrase = !row.en.is_empty() || !row.jp.is_empty();
+        if row.section.is_empty() && !is_phrase {
+            continue;
+        }
+
+        let section_idx = match lesson
+            .sections
+            .iter()
+            .position(|section| section.name == row.section)
+        {
+            Some(section_idx) => section_idx,
+            None => {
+                lesson.sections.push(Section {
+                    name: row.section,
+                    phrases: Vec::new(),
+                });
+                lesson.sections.len() - 1
+            }
+        };
+        // a section without phrases
+        if !is_phrase {
+            continue;
+        }
+        lesson.sections[section_idx].phrases.push(Phrase {
+            id: row.id,
+            en: row.en,
+            jp: row.jp,
+            kanji: (!row.kanji.is_empty()).then_some(row.kanji),
+            part_of_speech: row.part_of_speech,
+            notes: (!row.notes.is_empty()).then_some(row.notes),
+            examples: row
+                .examples
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let (jp, en) = line.split_once(EXAMPLE_SEPARATOR).unwrap_or((line, ""));
+                    Example {
+                        jp: String::from(jp),
+                        en: String::from(en),
+                    }
+                })
+                .collect(),
+            tags: split(&row.tags),
+            former_ids: split(&row.former_ids),
+        });
+    }
+    book.assign_ids(lessons::VERSION);
+    book.check_ids()?;
+    Ok(book)
+}
+
+fn split(list: &str) -> Vec<String> {
+    list.split(';')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Resources;
+
+    fn round_trip(book: &Book, delimiter: Delimiter) -> Book {
+        let mut output = Vec::<u8>::new();
+        export(book, &mut output, delimiter).unwrap();
+        import(output.as_slice(), delimiter).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let book = Book::from_file(&Resources::default().lessons_path()).unwrap();
+        for delimiter in [Delimiter::Comma, Delimiter::Tab] {
+            let result = round_trip(&book, delimiter);
+            assert_eq!(result, book, "Failed for delimiter: {:?}", delimiter);
+            // and back to the same lessons.json
+            assert_eq!(Book::from_json(&result.to_json().unwrap()).unwrap(), book);
+        }
+    }
+
+    #[test]
+    fn test_empty_lessons() {
+        // nothing but the title, source and names of the lessons and sections
+        let book = Book::from_json(
+            r#"{"version": 2, "title": "Class notes", "source": "Genki I, 3rd edition", "lessons": [
+                {"index": 1, "name_en": "New Friends", "name_jp": "ともだち", "sections": [
+                    {"name": "People", "phrases": [{"id": "person", "en": "person", "jp": "ひと"}]},
+                    {"name": "Later", "phrases": []}
+                ]},
+                {"index": 2, "name_en": "Shopping", "name_jp": "かいもの", "sections": []}
+            ]}"#,
+        )
+        .unwrap();
+        for delimiter in [Delimiter::Comma, Delimiter::Tab] {
+            assert_eq!(
+                round_trip(&book, delimiter),
+                book,
+                "Failed for delimiter: {:?}",
+                delimiter
+            );
+        }
+    }
+
+    #[test]
+    fn test_awkward_values() {
+        let book = Book::from_json(
+            r#"{"lessons": [{"index": 7, "name_en": "Odd, \"quoted\"", "name_jp": "へん", "sections": [
+                {"name": "Tabs\tand, commas", "phrases": [
+                    {"en": "line\nbreak", "jp": "かいぎょう", "tags": ["noun", "u-verb"], "former_ids": ["7/0/0", "old"]},
+                    {"en": " spaced ", "jp": "すき ま", "kanji": "隙間", "part_of_speech": "noun", "notes": "a gap,\n\"narrow\""},
+                    {"en": "to wait", "jp": "まつ", "part_of_speech": "u-verb", "examples": [
+                        {"jp": "ともだちを まちます。", "en": "I wait for a friend; then | go."},
+                        {"jp": "まって!"}
+                    ]}
+                ]}
+            ]}]}"#,
+        )
+        .unwrap();
+        for delimiter in [Delimiter::Comma, Delimiter::Tab] {
+            assert_eq!(
+                round_trip(&book, delimiter).lessons,
+                book.lessons,
+                "Failed for delimiter: {:?}",
+                delimiter
+            );
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        // only the required columns, rows of a lesson dont have to be together
+        let csv = "lesson,section,en,jp\n\
+                   2,Places,school,がっこう\n\
+                   1,People,student,がくせい\n\
+                   2,Places,house,いえ\n";
+        let book = import(csv.as_bytes(), Delimiter::Comma).unwrap();
+        assert_eq!(book.lessons.len(), 2);
+        assert_eq!(book.lessons[0].index, 2);
+        assert_eq!(book.lessons[0].sections[0].phrases.len(), 2);
+        assert_eq!(book.lessons[0].sections[0].phrases[1].kanji, None);
+
+        let test_cases = vec![
+            "lesson,section,en\n1,People,student\n",
+            "lesson,section,en,jp\none,People,student,がくせい\n",
+            "id,lesson,section,en,jp\na,1,People,student,がくせい\na,1,People,teacher,せんせい\n",
+        ];
+        for csv in test_cases {
+            assert!(
+                import(csv.as_bytes(), Delimiter::Comma).is_err(),
+                "Failed for csv: {}",
+                csv
+            );
+        }
+    }
+
+    #[test]
+    fn test_delimiter_from_path() {
+        let test_cases = vec![
+            ("decks/genki.csv", Some(Delimiter::Comma)),
+            ("genki.TSV", Some(Delimiter::Tab)),
+            ("lessons.json", None),
+            ("lessons", None),
+        ];
+        for (path, expected) in test_cases {
+            assert_eq!(
+                Delimiter::from_path(Path::new(path)),
+                expected,
+                "Failed for path: {}",
+                path
+            );
+        }
+    }
+}
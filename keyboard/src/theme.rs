@@ -0,0 +1,191 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    Monochrome,
+}
+
+impl ThemeName {
+    pub fn next(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::HighContrast,
+            ThemeName::HighContrast => ThemeName::Monochrome,
+            ThemeName::Monochrome => ThemeName::Dark,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::Monochrome => "monochrome",
+        }
+    }
+}
+
+impl FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        [
+            ThemeName::Dark,
+            ThemeName::Light,
+            ThemeName::HighContrast,
+            ThemeName::Monochrome,
+        ]
+        .into_iter()
+        .find(|name| name.label() == value)
+        .ok_or_else(|| {
+            format!("unknown theme '{value}', expected dark, light, high-contrast or monochrome")
+        })
+    }
+}
+
+// colours from the config file, replacing the ones of every theme
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Colors {
+    // titles, borders and the welcome art
+    pub accent: Option<Color>,
+    // text inside the panels
+    pub highlight: Option<Color>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Theme {
+    pub name: ThemeName,
+    // whole screen, under everything else
+    pub base: Style,
+    pub title: Style,
+    // borders, list entries and the welcome art
+    pub accent: Style,
+    // answers, input and session settings
+    pub text: Style,
+    // kanji candidates
+    pub muted: Style,
+    // cursor in lists and the highlighted kana
+    pub selected: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(ThemeName::default(), Colors::default())
+    }
+}
+
+impl Theme {
+    pub fn new(name: ThemeName, colors: Colors) -> Self {
+        let mut theme = Theme::preset(name);
+        if let Some(accent) = colors.accent {
+            theme.title = theme.title.fg(accent);
+            theme.accent = theme.accent.fg(accent);
+        }
+        if let Some(highlight) = colors.highlight {
+            theme.text = theme.text.fg(highlight);
+        }
+        theme
+    }
+
+    fn preset(name: ThemeName) -> Self {
+        let reversed = Style::new().add_modifier(Modifier::REVERSED);
+        match name {
+            ThemeName::Dark => Theme {
+                name,
+                base: Style::new(),
+                title: Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                accent: Style::new().fg(Color::Yellow),
+                text: Style::new().fg(Color::LightYellow),
+                muted: Style::new().fg(Color::DarkGray),
+                selected: reversed,
+            },
+            // dark colours that stay readable on a white background
+            ThemeName::Light => Theme {
+                name,
+                base: Style::new(),
+                title: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                accent: Style::new().fg(Color::Blue),
+                text: Style::new().fg(Color::Black),
+                muted: Style::new().fg(Color::DarkGray),
+                selected: reversed,
+            },
+            // white on black only, nothing relies on telling colours apart
+            ThemeName::HighContrast => Theme {
+                name,
+                base: Style::new().fg(Color::White).bg(Color::Black),
+                title: Style::new()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                accent: Style::new().fg(Color::White),
+                text: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+                muted: Style::new().fg(Color::White),
+                selected: reversed.add_modifier(Modifier::BOLD),
+            },
+            // the terminal's own colours, emphasis through modifiers
+            ThemeName::Monochrome => Theme {
+                name,
+                base: Style::new(),
+                title: Style::new().add_modifier(Modifier::BOLD),
+                accent: Style::new(),
+                text: Style::new(),
+                muted: Style::new().add_modifier(Modifier::DIM),
+                selected: reversed,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_name() {
+        let mut name = ThemeName::default();
+        for _ in 0..4 {
+            assert_eq!(name.label().parse::<ThemeName>(), Ok(name));
+            name = name.next();
+        }
+        assert_eq!(name, ThemeName::Dark);
+        assert!("solarized".parse::<ThemeName>().is_err());
+    }
+
+    #[test]
+    fn test_colors() {
+        let colors = Colors {
+            accent: Some(Color::Cyan),
+            highlight: None,
+        };
+        let test_cases = vec![
+            ThemeName::Dark,
+            ThemeName::Light,
+            ThemeName::HighContrast,
+            ThemeName::Monochrome,
+        ];
+        for name in test_cases {
+            let theme = Theme::new(name, colors);
+            let preset = Theme::preset(name);
+            assert_eq!(
+                theme.title.fg,
+                Some(Color::Cyan),
+                "Failed for theme: {:?}",
+                name
+            );
+            assert_eq!(
+                theme.accent.fg,
+                Some(Color::Cyan),
+                "Failed for theme: {:?}",
+                name
+            );
+            assert_eq!(theme.title.add_modifier, preset.title.add_modifier);
+            assert_eq!(theme.text, preset.text, "Failed for theme: {:?}", name);
+        }
+    }
+}
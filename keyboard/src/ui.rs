@@ -1,31 +1,110 @@
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style, Stylize},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     symbols::border,
     text::{Line, Span, Text},
-    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        Block, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState, Wrap,
+    },
 };
 
-use crate::app::{App, CurrentScreen, CurrentSelection, TranslationDirection};
+use crate::app::{Action, App, CurrentScreen, CurrentSelection, Drill, TranslationDirection};
+use crate::furigana::Ruby;
+use crate::kana_drill;
+use crate::lessons::Phrase;
+
+use crate::theme::Theme;
 
 pub fn ui(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    frame.render_widget(Block::new().style(theme.base), frame.area());
+    let [screen_area, hints_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
     match app.context.current_screen {
         CurrentScreen::Welcome => {
-            render_welcome(frame);
+            render_welcome(frame, screen_area, theme);
+        }
+        CurrentScreen::BookSelect => {
+            render_book_select(frame, screen_area, app, theme);
         }
         CurrentScreen::LessonSelect => {
-            render_lesson_select(frame, app);
+            render_lesson_select(frame, screen_area, app, theme);
         }
         CurrentScreen::Review => {
-            render_review(frame, app);
+            render_review(frame, screen_area, app, theme);
+        }
+        CurrentScreen::Browse => {
+            render_browse(frame, screen_area, app, theme);
         }
+        CurrentScreen::Search => {
+            render_search(frame, screen_area, app, theme);
+        }
+    }
+    render_hints(frame, hints_area, app, theme);
+    if app.show_help {
+        render_help(frame, screen_area, app, theme);
+    }
+}
+
+fn screen_name(screen: CurrentScreen) -> &'static str {
+    match screen {
+        CurrentScreen::Welcome => "Welcome",
+        CurrentScreen::BookSelect => "Books",
+        CurrentScreen::LessonSelect => "Lessons",
+        CurrentScreen::Review => "Review",
+        CurrentScreen::Browse => "Browse",
+        CurrentScreen::Search => "Search",
+    }
+}
+
+fn render_hints(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let mut spans = vec![Span::raw(" ")];
+    for (key, label) in app.keymap.hints(app.context.current_screen) {
+        spans.push(Span::styled(key, theme.accent));
+        spans.push(Span::styled(format!(" {label}   "), theme.muted));
     }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
-fn render_welcome(frame: &mut Frame) {
-    let title = Line::from(" Genki Japanese Keyboard ".yellow().bold());
-    let instructions = Line::from(vec![" げんき ".yellow().bold()]);
+fn render_help(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let help = app.keymap.help(app.context.current_screen);
+    let key_width = help
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Line> = help
+        .into_iter()
+        .map(|(keys, text)| {
+            Line::from(vec![
+                Span::styled(format!(" {keys:<key_width$}  "), theme.accent),
+                Span::styled(text, theme.text),
+            ])
+        })
+        .collect();
+
+    let [popup] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 3;
+    let [popup] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(popup);
+    let title = format!(" Help - {} ", screen_name(app.context.current_screen));
+    let help_text = Paragraph::new(lines).block(
+        Block::bordered()
+            .title(Line::from(Span::styled(title, theme.title)))
+            .title_bottom(Line::from(" Esc: close ").right_aligned())
+            .border_set(border::THICK)
+            .style(theme.base),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(help_text, popup);
+}
+
+fn render_welcome(frame: &mut Frame, area: Rect, theme: &Theme) {
+    let title = Line::from(Span::styled(" Genki Japanese Keyboard ", theme.title));
+    let instructions = Line::from(vec![Span::styled(" げんき ", theme.title)]);
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
@@ -41,29 +120,93 @@ fn render_welcome(frame: &mut Frame) {
     ZZZzz /,`.-'`'    -.  ;-;;,_
           |,4-  ) )-,_. ,\ (  `'-'
     '---''(_/--'  `-'\_)",
-        Style::default().fg(Color::Yellow),
+        theme.accent,
     ))
     .centered()
     .block(block);
 
-    frame.render_widget(start, frame.area());
+    frame.render_widget(start, area);
 }
 
-fn render_lesson_select(frame: &mut Frame, app: &App) {
+fn render_book_select(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let rows: Vec<Row> = app
+        .library
+        .shelves
+        .iter()
+        .enumerate()
+        .map(|(shelf_idx, shelf)| {
+            let marker = if shelf_idx == app.open_shelf_idx {
+                "●"
+            } else {
+                " "
+            };
+            Row::new(vec![
+                Cell::from(marker),
+                Cell::from(shelf.name.clone()),
+                Cell::from(shelf.book.lessons.len().to_string()),
+                Cell::from(shelf.phrase_count().to_string()),
+                Cell::from(shelf.path.display().to_string()),
+            ])
+            .style(theme.text)
+        })
+        .collect();
+
+    let header = Row::new(vec!["", "Book", "Lessons", "Phrases", "File"]).style(theme.title);
+    let widths = [
+        Constraint::Length(1),
+        Constraint::Percentage(35),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::bordered()
+                .title(Line::from(Span::styled(" Books ", theme.title)))
+                .title(Line::from(format!(" {} books ", app.library.shelves.len())).right_aligned())
+                .border_set(border::THICK)
+                .style(theme.accent),
+        )
+        .row_highlight_style(theme.selected)
+        .highlight_symbol("‣");
+
+    let mut table_state = TableState::default();
+    table_state.select(Some(app.context.shelf_idx));
+    frame.render_stateful_widget(table, area, &mut table_state);
+}
+
+fn render_lesson_select(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let [lists_chunk, options_chunk] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(7)]).areas(area);
+
     let selection_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(frame.area());
+        .split(lists_chunk);
 
     // draw lesson selection
     let mut lesson_items = Vec::<ListItem>::new();
-    for lesson in &app.book.lessons {
+    for (lesson_idx, lesson) in app.book.lessons.iter().enumerate() {
+        // mark whole lessons and lessons with some selected sections
+        let marker = if app.context.selected_lessons.contains(&lesson_idx) {
+            "●"
+        } else if app
+            .context
+            .selected_sections
+            .iter()
+            .any(|&(selected_lesson_idx, _)| selected_lesson_idx == lesson_idx)
+        {
+            "◐"
+        } else {
+            " "
+        };
         lesson_items.push(ListItem::new(Line::from(Span::styled(
             format!(
-                " [{}] {} - {} ",
-                lesson.index, lesson.name_en, lesson.name_jp
+                "{} [{}] {} - {} ",
+                marker, lesson.index, lesson.name_en, lesson.name_jp
             ),
-            Style::default().fg(Color::Yellow),
+            theme.accent,
         ))));
     }
 
@@ -71,20 +214,26 @@ fn render_lesson_select(frame: &mut Frame, app: &App) {
     lesson_state.select(Some(app.context.lesson_idx));
 
     let mut lesson_border_thinkness = border::PLAIN;
-    match app.context.current_selection {
-        CurrentSelection::Lesson => {
-            lesson_border_thinkness = border::THICK;
-        }
-        _ => {}
+    if let CurrentSelection::Lesson = app.context.current_selection {
+        lesson_border_thinkness = border::THICK;
     }
 
+    // name the book when there are several to choose from
+    let lessons_title = if app.library.shelves.len() > 1 {
+        format!(
+            " Lessons - {} ",
+            app.library.shelves[app.open_shelf_idx].name
+        )
+    } else {
+        String::from(" Lessons ")
+    };
     let lesson_list = List::new(lesson_items)
         .block(
             Block::bordered()
-                .title(Line::from(" Lessons ".yellow().bold()))
+                .title(Line::from(Span::styled(lessons_title, theme.title)))
                 .border_set(lesson_border_thinkness),
         )
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_style(theme.selected)
         .highlight_symbol("‣");
     frame.render_stateful_widget(lesson_list, selection_chunks[0], &mut lesson_state);
 
@@ -92,79 +241,239 @@ fn render_lesson_select(frame: &mut Frame, app: &App) {
     let mut section_items = Vec::<ListItem>::new();
     let mut section_state = ListState::default();
     let mut section_border_thinkness = border::PLAIN;
-    match app.context.current_selection {
-        CurrentSelection::Section => {
-            section_border_thinkness = border::THICK;
-            assert!(app.context.lesson_idx < app.book.lessons.len());
-            let lesson = &app.book.lessons[app.context.lesson_idx];
-            for section in &lesson.sections {
-                section_items.push(ListItem::new(Line::from(Span::styled(
-                    format!(" [{}] {} ", section_items.len(), section.name),
-                    Style::default().fg(Color::Yellow),
-                ))));
-            }
-            section_state.select(app.context.section_idx);
+    if let CurrentSelection::Section = app.context.current_selection {
+        section_border_thinkness = border::THICK;
+        assert!(app.context.lesson_idx < app.book.lessons.len());
+        let lesson = &app.book.lessons[app.context.lesson_idx];
+        let lesson_selected = app
+            .context
+            .selected_lessons
+            .contains(&app.context.lesson_idx);
+        for section in &lesson.sections {
+            let section_selected = app
+                .context
+                .selected_sections
+                .contains(&(app.context.lesson_idx, section_items.len()));
+            let marker = if lesson_selected || section_selected {
+                "●"
+            } else {
+                " "
+            };
+            section_items.push(ListItem::new(Line::from(Span::styled(
+                format!("{} [{}] {} ", marker, section_items.len(), section.name),
+                theme.accent,
+            ))));
         }
-        _ => {}
+        section_state.select(app.context.section_idx);
     }
 
     let section_list = List::new(section_items)
         .block(
             Block::bordered()
-                .title(Line::from(" Sections ".yellow().bold()))
+                .title(Line::from(Span::styled(" Sections ", theme.title)))
                 .border_set(section_border_thinkness),
         )
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_style(theme.selected)
         .highlight_symbol("‣");
 
     frame.render_stateful_widget(section_list, selection_chunks[1], &mut section_state);
+
+    // draw session options, with the keys that change them
+    let key_for = |action| {
+        app.keymap
+            .key_for(CurrentScreen::LessonSelect, action)
+            .unwrap_or_default()
+    };
+    let options_text = Paragraph::new(Text::from(vec![
+        Line::from(vec![
+            Span::raw(" direction: "),
+            Span::styled(app.context.direction_mode.label(), theme.text),
+            Span::raw(format!(
+                "  ({}: change, {}/{}: weight)",
+                key_for(Action::CycleDirectionMode),
+                key_for(Action::AdjustDirectionWeight(10)),
+                key_for(Action::AdjustDirectionWeight(-10))
+            )),
+        ]),
+        Line::from(vec![
+            Span::raw(" drill:     "),
+            Span::styled(app.context.drill.label(), theme.text),
+            Span::raw(format!("  ({}: change)", key_for(Action::CycleDrill))),
+        ]),
+        // the kana drill asks its pools, not the chosen sections
+        match app.context.drill {
+            Drill::Kana => Line::from(vec![
+                Span::raw(" practice:  "),
+                Span::styled(kana_drill::pools_label(&app.context.kana_pools), theme.text),
+                Span::raw(format!("  ({}: change)", key_for(Action::CycleKanaPools))),
+            ]),
+            _ => Line::from(vec![
+                Span::raw(" practice:  "),
+                Span::styled(format!("{} sections", app.session_pool().len()), theme.text),
+                Span::raw(format!(
+                    "  ({}: toggle, {}: clear)",
+                    key_for(Action::ToggleSelection),
+                    key_for(Action::ClearSelection)
+                )),
+            ]),
+        },
+        Line::from(vec![
+            Span::raw(" theme:     "),
+            Span::styled(app.theme.name.label(), theme.text),
+            Span::raw(format!("  ({}: change)", key_for(Action::CycleTheme))),
+        ]),
+        Line::from(vec![
+            Span::raw(" seed:      "),
+            Span::styled(app.context.seed.to_string(), theme.text),
+        ]),
+    ]))
+    .block(Block::bordered().title(Line::from(Span::styled(" Session ", theme.title))));
+    frame.render_widget(options_text, options_chunk);
 }
 
-fn render_review(frame: &mut Frame, app: &App) {
+fn render_review(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
     let [review_chunk, japanese_chunk, romanji_chunk] = Layout::vertical([
         Constraint::Percentage(35),
         Constraint::Percentage(35),
         Constraint::Percentage(30),
     ])
-    .areas(frame.area());
+    .areas(area);
 
     let [question_chunk, answer_selector_chunk] =
         Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
             .areas(review_chunk);
 
-    let lessons = &app.book.lessons;
-    assert!(app.context.lesson_idx < lessons.len());
-    let lesson = &lessons[app.context.lesson_idx];
-    assert!(app.context.section_idx.expect("missing section index") < lesson.sections.len());
-    let section = &lesson.sections[app.context.section_idx.unwrap()];
-    assert!(app.context.phrase_idx < section.phrases.len());
-    let phrase = &section.phrases[app.context.phrase_idx];
-
-    let question_title: String;
-    match app.context.translation_direction {
-        TranslationDirection::ToEN => {
-            if let Some(kanji) = &phrase.kanji {
+    let (lesson, section, phrase) = app.question();
+
+    let mut question_title: String;
+    // readings above the kanji when they line up with the kana
+    let mut rubies = None;
+    match (app.context.drill, app.context.translation_direction) {
+        (Drill::Reading, _) => {
+            question_title = format!(" Read the kanji\n'{}'", reading_prompt(phrase));
+        }
+        (Drill::KanjiReading, _) => {
+            question_title = format!(
+                " Read the kanji\n'{}'\n\nas in {}",
+                reading_prompt(phrase),
+                phrase.en
+            );
+        }
+        (Drill::Writing, _) => {
+            question_title = format!(" Write in kanji\n'{}' - '{}'", phrase.jp, phrase.en);
+        }
+        (Drill::Kana, TranslationDirection::ToEN) => {
+            question_title = format!(" Read the kana\n'{}'", phrase.jp);
+        }
+        (Drill::Kana, TranslationDirection::ToJP) => {
+            question_title = format!(
+                " Write in {}\n'{}'",
+                kana_drill::script_label(&phrase.jp),
+                phrase.en
+            );
+        }
+        (Drill::Translate, TranslationDirection::ToEN) => {
+            if let Some(kanji) = &phrase.kanji
+                && app.preferences.hide_furigana
+            {
+                question_title = format!(" Translate from Japanese\n'{}'", kanji);
+            } else if let Some(kanji) = &phrase.kanji {
                 question_title = format!(" Translate from Japanese\n'{}' - '{}'", phrase.jp, kanji);
+                rubies = app.furigana.align(kanji, &phrase.jp);
             } else {
                 question_title = format!(" Translate from Japanese\n'{}'", phrase.jp);
             }
         }
-        TranslationDirection::ToJP => {
+        (Drill::Translate, TranslationDirection::ToJP) => {
             question_title = format!(" Translate from English\n'{}'", phrase.en);
         }
     }
-    let question_text = Paragraph::new(question_title)
-        .light_yellow()
-        .block(
-            Block::bordered()
-                .title(format!(" Lesson {} - {} ", lesson.index, section.name))
-                .yellow(),
-        )
-        .wrap(Wrap { trim: true });
+    // kana drills arent lessons of the book
+    let question_block_title = match app.context.drill {
+        Drill::Kana => format!(" {} - {} ", lesson.name_en, section.name),
+        _ => format!(" Lesson {} - {} ", lesson.index, section.name),
+    };
+    let mut question_block = Block::bordered()
+        .title(question_block_title)
+        .style(theme.accent);
+    if let Some(session_len) = app.context.session_len {
+        let question_number = (app.context.answered + 1).min(session_len);
+        question_block = question_block
+            .title(Line::from(format!(" {}/{} ", question_number, session_len)).right_aligned());
+    }
+    if let Some(mark) = app.progress.mark(&app.question_key())
+        && !app.context.session_finished()
+    {
+        question_block = question_block.title_bottom(Line::from(format!(" {} ", mark.label())));
+    }
+    if app.context.session_finished() {
+        question_title = format!(
+            " Session complete\n{} questions answered\n\npress Enter to return",
+            app.context.answered
+        );
+        rubies = None;
+    }
+    let question_text = match rubies {
+        // spaces line up the readings, so they cant be trimmed
+        Some(rubies) => {
+            let (readings, kanji) = ruby_lines(&rubies);
+            Paragraph::new(vec![
+                Line::from("Translate from Japanese"),
+                Line::styled(readings, theme.muted),
+                Line::from(kanji),
+            ])
+            .wrap(Wrap { trim: false })
+        }
+        None => Paragraph::new(question_title).wrap(Wrap { trim: true }),
+    }
+    .style(theme.text)
+    .block(question_block);
     frame.render_widget(question_text, question_chunk);
 
     let mut answer_title = String::new();
-    if let Some(prev_phrase_idx) = app.context.prev_phrase_idx {
+    if let Some(phrase) = app.prev_question()
+        && app.context.drill != Drill::Translate
+    {
+        let prev_answer = app.context.prev_answer.as_deref().unwrap_or_default();
+        answer_title = match app.context.drill {
+            Drill::Writing => format!(
+                " Write in kanji\n'{}' - '{}'\n\ncorrect answer: '{}'\nyour answer:    '{}'",
+                phrase.jp,
+                phrase.en,
+                reading_prompt(phrase),
+                prev_answer
+            ),
+            Drill::Kana => match app.context.prev_translation_direction {
+                Some(TranslationDirection::ToJP) => format!(
+                    " Write in {}\n'{}'\n\ncorrect answer: '{}'\nyour answer:    '{}'",
+                    kana_drill::script_label(&phrase.jp),
+                    phrase.en,
+                    phrase.jp,
+                    prev_answer
+                ),
+                _ => format!(
+                    " Read the kana\n'{}'\n\ncorrect answer: '{}'\nyour answer:    '{}'",
+                    phrase.jp, phrase.en, prev_answer
+                ),
+            },
+            _ => format!(
+                " Read the kanji\n'{}'\n\ncorrect answer: '{}'\nyour answer:    '{}'",
+                reading_prompt(phrase),
+                phrase.jp,
+                prev_answer
+            ),
+        };
+        if app.context.drill == Drill::Reading {
+            answer_title.push_str(&format!("\nmeaning:        '{}'", phrase.en));
+        }
+        if app.context.drill == Drill::Kana {
+            let progress = app.progress.get(&app.session_key(phrase));
+            answer_title.push_str(&format!(
+                "\naccuracy:       {} of {}",
+                progress.correct, progress.seen
+            ));
+        }
+    } else if let Some(phrase) = app.prev_question() {
         let prev_translation_direction = app
             .context
             .prev_translation_direction
@@ -174,17 +483,6 @@ fn render_review(frame: &mut Frame, app: &App) {
             .prev_answer
             .as_ref()
             .expect("previous answer not set");
-        assert!(app.context.lesson_idx < app.book.lessons.len());
-        let lesson = &app.book.lessons[app.context.lesson_idx];
-        assert!(
-            app.context
-                .prev_section_idx
-                .expect("previous section index not set")
-                < lesson.sections.len()
-        );
-        let section = &lesson.sections[app.context.prev_section_idx.unwrap()];
-        assert!(prev_phrase_idx < section.phrases.len());
-        let phrase = &section.phrases[prev_phrase_idx];
         match prev_translation_direction {
             TranslationDirection::ToEN => {
                 if let Some(kanji) = &phrase.kanji {
@@ -224,9 +522,24 @@ fn render_review(frame: &mut Frame, app: &App) {
             }
         }
     }
+    if let Some(phrase) = app.prev_question() {
+        let details = phrase_details(phrase);
+        if !details.is_empty() {
+            answer_title = format!("{answer_title}\n\n{}", details.join("\n"));
+        }
+    }
+    let answer_block_title = match app.context.prev_correct {
+        Some(true) => " answer - correct ",
+        Some(false) => " answer - wrong ",
+        None => " answer ",
+    };
     let answer_text = Paragraph::new(answer_title)
-        .light_yellow()
-        .block(Block::bordered().title(format!(" answer ")).yellow())
+        .style(theme.text)
+        .block(
+            Block::bordered()
+                .title(answer_block_title)
+                .style(theme.accent),
+        )
         .wrap(Wrap { trim: true });
     frame.render_widget(answer_text, answer_selector_chunk);
 
@@ -238,26 +551,26 @@ fn render_review(frame: &mut Frame, app: &App) {
     .areas(japanese_chunk);
 
     // kana box with highlighting
-    let kana = app.get_kana();
+    let kana = app.composer.get_kana();
     let mut left = String::new();
     let mut middle = String::new();
     let mut right = String::new();
     if kana.chars().count() > 0 {
-        assert!(app.kana_offset + app.kana_len <= kana.chars().count());
-        assert!(app.kana_len >= 1);
+        assert!(app.composer.kana_offset + app.composer.kana_len <= kana.chars().count());
+        assert!(app.composer.kana_len >= 1);
         middle = kana
             .chars()
-            .take(app.kana_offset + app.kana_len)
-            .skip(app.kana_offset)
+            .take(app.composer.kana_offset + app.composer.kana_len)
+            .skip(app.composer.kana_offset)
             .collect();
-        if app.kana_offset > 0 {
-            left = kana.chars().take(app.kana_offset).collect();
+        if app.composer.kana_offset > 0 {
+            left = kana.chars().take(app.composer.kana_offset).collect();
         }
-        if app.kana_offset + app.kana_len < kana.chars().count() {
+        if app.composer.kana_offset + app.composer.kana_len < kana.chars().count() {
             right = kana
                 .chars()
                 .take(kana.chars().count())
-                .skip(app.kana_offset + app.kana_len)
+                .skip(app.composer.kana_offset + app.composer.kana_len)
                 .collect();
         }
     }
@@ -265,51 +578,565 @@ fn render_review(frame: &mut Frame, app: &App) {
     // highlight selected kana
     let kana_formatted = Text::from(vec![Line::from(vec![
         Span::raw(left),
-        Span::styled(&middle, Style::default().add_modifier(Modifier::REVERSED)),
+        Span::styled(&middle, theme.selected),
         Span::raw(right),
     ])]);
 
     let kana_text = Paragraph::new(kana_formatted)
-        .light_yellow()
-        .block(Block::bordered().title(" kana ").yellow())
+        .style(theme.text)
+        .block(Block::bordered().title(" kana ").style(theme.accent))
         .wrap(Wrap { trim: true });
     frame.render_widget(kana_text, kana_chunk);
 
     // kanji selection
-    let kanji = &app.highlighted_kanji;
+    let kanji = &app.composer.highlighted_kanji;
     let mut kanji_items = Vec::<ListItem>::new();
     for kanji_char in kanji {
         kanji_items.push(ListItem::new(
-            Line::from(Span::styled(
-                format!(" {} ", kanji_char),
-                Style::default().fg(Color::LightYellow),
-            ))
-            .centered(),
+            Line::from(Span::styled(format!(" {} ", kanji_char), theme.text)).centered(),
         ));
     }
 
     let mut kanji_state = ListState::default();
-    if kanji.len() > 0 {
-        kanji_state.select(Some(app.context.kanji_offset));
+    if !kanji.is_empty() {
+        kanji_state.select(Some(app.composer.kanji_offset));
     }
 
     let kanji_list = List::new(kanji_items)
-        .dark_gray()
-        .block(Block::bordered().title(" kanji ").yellow())
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        .style(theme.muted)
+        .block(Block::bordered().title(" kanji ").style(theme.accent))
+        .highlight_style(theme.selected);
 
     frame.render_stateful_widget(kanji_list, kanji_selector_chunk, &mut kanji_state);
 
     // kanji text box
-    let complete_text = Paragraph::new(app.get_kanji())
-        .light_yellow()
-        .block(Block::bordered().title(" complete ").yellow())
+    let complete_text = Paragraph::new(app.composer.get_kanji())
+        .style(theme.text)
+        .block(Block::bordered().title(" complete ").style(theme.accent))
         .wrap(Wrap { trim: true });
     frame.render_widget(complete_text, kanji_chunk);
 
     // romanji text box
-    let romanji_text = Paragraph::new(app.get_romanji())
+    let romanji_text = Paragraph::new(app.composer.get_romanji())
         .block(Block::bordered().title(" romanji "))
         .wrap(Wrap { trim: true });
     frame.render_widget(romanji_text, romanji_chunk);
 }
+
+// the kanji of a reading or writing question, phrases without any are
+// never asked
+fn reading_prompt(phrase: &Phrase) -> &str {
+    phrase.kanji.as_deref().unwrap_or(&phrase.jp)
+}
+
+// the readings line and the kanji line below it, every part is as wide
+// as the wider of its kanji and its reading
+fn ruby_lines(rubies: &[Ruby]) -> (String, String) {
+    // the quote in front of the kanji
+    let mut readings = String::from(" ");
+    let mut kanji = String::from("'");
+    for ruby in rubies {
+        let reading = ruby.reading.as_deref().unwrap_or_default();
+        let reading_width = Span::raw(reading).width();
+        let base_width = Span::raw(&ruby.base).width();
+        let width = reading_width.max(base_width);
+        readings.push_str(reading);
+        readings.push_str(&" ".repeat(width - reading_width));
+        kanji.push_str(&ruby.base);
+        kanji.push_str(&" ".repeat(width - base_width));
+    }
+    (
+        readings.trim_end().to_string(),
+        format!("{}'", kanji.trim_end()),
+    )
+}
+
+// part of speech, tags, notes and examples, whichever the phrase has
+fn phrase_details(phrase: &Phrase) -> Vec<String> {
+    let mut details = Vec::<String>::new();
+    let kinds: Vec<&str> = phrase
+        .part_of_speech
+        .iter()
+        .map(|part_of_speech| part_of_speech.label())
+        .chain(phrase.tags.iter().map(String::as_str))
+        .collect();
+    if !kinds.is_empty() {
+        details.push(kinds.join(" · "));
+    }
+    if let Some(notes) = &phrase.notes {
+        details.push(notes.clone());
+    }
+    for example in &phrase.examples {
+        if example.en.is_empty() {
+            details.push(format!("「{}」", example.jp));
+        } else {
+            details.push(format!("「{}」 {}", example.jp, example.en));
+        }
+    }
+    details
+}
+
+fn render_browse(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let browse_rows = app.browse_rows();
+    // notes and examples of the highlighted phrase below the table
+    let details = browse_rows
+        .get(app.context.browse_idx)
+        .map(|&(lesson_idx, section_idx, phrase_idx)| {
+            phrase_details(&app.book[lesson_idx].sections[section_idx].phrases[phrase_idx])
+        })
+        .unwrap_or_default();
+    let details_height = if details.is_empty() {
+        0
+    } else {
+        details.len() as u16 + 2
+    };
+    let [search_chunk, table_chunk, details_chunk] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(details_height),
+    ])
+    .areas(area);
+
+    render_search_box(frame, search_chunk, &app.context.browse_query, theme);
+
+    let lesson = &app.book[app.context.lesson_idx];
+    let scope = match app.context.section_idx {
+        Some(section_idx) => lesson.sections[section_idx].name.clone(),
+        None => lesson.name_en.clone(),
+    };
+    let converter = app.composer.kana_converter();
+    let rows: Vec<Row> = browse_rows
+        .iter()
+        .map(|&(lesson_idx, section_idx, phrase_idx)| {
+            let phrase = &app.book[lesson_idx].sections[section_idx].phrases[phrase_idx];
+            let key = app.progress_key(phrase);
+            let mark = app.progress.mark(&key).map(|mark| mark.symbol());
+            Row::new(vec![
+                Cell::from(format!(
+                    "{:<3} {}",
+                    app.progress.get(&key).indicator(),
+                    mark.unwrap_or_default()
+                )),
+                Cell::from(phrase.en.clone()),
+                Cell::from(phrase.jp.clone()),
+                Cell::from(phrase.kanji.clone().unwrap_or_default()),
+                Cell::from(converter.to_romanji(&phrase.jp)),
+                Cell::from(
+                    phrase
+                        .part_of_speech
+                        .map(|part_of_speech| part_of_speech.label())
+                        .unwrap_or_default(),
+                ),
+            ])
+            .style(theme.text)
+        })
+        .collect();
+
+    let header =
+        Row::new(vec!["", "English", "Kana", "Kanji", "Romaji", "Type"]).style(theme.title);
+    let widths = [
+        Constraint::Length(5),
+        Constraint::Percentage(30),
+        Constraint::Percentage(20),
+        Constraint::Percentage(14),
+        Constraint::Fill(1),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::bordered()
+                .title(Line::from(Span::styled(
+                    format!(" Lesson {} - {} ", lesson.index, scope),
+                    theme.title,
+                )))
+                .title(Line::from(format!(" {} phrases ", browse_rows.len())).right_aligned())
+                .border_set(border::THICK)
+                .style(theme.accent),
+        )
+        .row_highlight_style(theme.selected);
+
+    let mut table_state = TableState::default();
+    if !browse_rows.is_empty() {
+        table_state.select(Some(app.context.browse_idx));
+    }
+    frame.render_stateful_widget(table, table_chunk, &mut table_state);
+
+    if !details.is_empty() {
+        let details_text = Paragraph::new(details.join("\n"))
+            .style(theme.text)
+            .block(Block::bordered().title(" details ").style(theme.accent))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(details_text, details_chunk);
+    }
+}
+
+fn render_search_box(frame: &mut Frame, area: Rect, query: &str, theme: &Theme) {
+    let search_text = Paragraph::new(Line::from(vec![
+        Span::raw(" "),
+        Span::styled(query, theme.text),
+    ]))
+    .block(
+        Block::bordered()
+            .title(Line::from(Span::styled(" Search ", theme.title)))
+            .style(theme.accent),
+    );
+    frame.render_widget(search_text, area);
+}
+
+fn render_search(frame: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let [search_chunk, table_chunk] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+    render_search_box(frame, search_chunk, &app.context.search_query, theme);
+
+    // every hit names the lesson and section it is in
+    let search_rows = app.search_rows();
+    let rows: Vec<Row> = search_rows
+        .iter()
+        .map(|&(lesson_idx, section_idx, phrase_idx)| {
+            let lesson = &app.book[lesson_idx];
+            let section = &lesson.sections[section_idx];
+            let phrase = &section.phrases[phrase_idx];
+            Row::new(vec![
+                Cell::from(format!("[{}] {}", lesson.index, section.name)),
+                Cell::from(phrase.en.clone()),
+                Cell::from(phrase.jp.clone()),
+                Cell::from(phrase.kanji.clone().unwrap_or_default()),
+            ])
+            .style(theme.text)
+        })
+        .collect();
+
+    let header = Row::new(vec!["Lesson", "English", "Kana", "Kanji"]).style(theme.title);
+    let widths = [
+        Constraint::Percentage(30),
+        Constraint::Percentage(32),
+        Constraint::Percentage(22),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::bordered()
+                .title(Line::from(Span::styled(" All lessons ", theme.title)))
+                .title(Line::from(format!(" {} phrases ", search_rows.len())).right_aligned())
+                .border_set(border::THICK)
+                .style(theme.accent),
+        )
+        .row_highlight_style(theme.selected);
+
+    let mut table_state = TableState::default();
+    if !search_rows.is_empty() {
+        table_state.select(Some(app.context.search_idx));
+    }
+    frame.render_stateful_widget(table, table_chunk, &mut table_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::tests::add_deck;
+
+    use std::{env, fs, path::PathBuf};
+
+    use ratatui::{
+        Terminal,
+        backend::TestBackend,
+        buffer::Buffer,
+        crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+        style::Modifier,
+    };
+
+    use crate::app::{DirectionMode, Transition};
+    use crate::preferences::Preferences;
+    use crate::progress::Mark;
+    use crate::resources::Resources;
+
+    const WIDTH: u16 = 100;
+    const HEIGHT: u16 = 30;
+
+    // drive the app the same way run_app does
+    fn press(app: &mut App, keys: &[(KeyCode, KeyModifiers)]) {
+        for &(code, modifiers) in keys {
+            if let Some(action) = app
+                .keymap
+                .action_for(app.context.current_screen, KeyEvent::new(code, modifiers))
+            {
+                assert_ne!(app.handle_event(action), Transition::Quit);
+            }
+        }
+    }
+
+    fn key(code: KeyCode) -> (KeyCode, KeyModifiers) {
+        (code, KeyModifiers::NONE)
+    }
+
+    fn shift(code: KeyCode) -> (KeyCode, KeyModifiers) {
+        (code, KeyModifiers::SHIFT)
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        let keys: Vec<(KeyCode, KeyModifiers)> = text
+            .chars()
+            .map(|value| key(KeyCode::Char(value)))
+            .collect();
+        press(app, &keys);
+    }
+
+    // rendered text followed by the runs of reversed cells on every row
+    fn render(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer: &Buffer = terminal.backend().buffer();
+
+        let mut text = String::new();
+        let mut reversed = String::new();
+        for y in 0..buffer.area.height {
+            let mut skip: usize = 0;
+            let mut run_start: Option<u16> = None;
+            for x in 0..buffer.area.width {
+                let cell = &buffer[(x, y)];
+                // cells hidden by a wide symbol belong to that symbol
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                text.push_str(cell.symbol());
+                skip = Span::raw(cell.symbol()).width().saturating_sub(1);
+
+                let is_reversed = cell.modifier.contains(Modifier::REVERSED);
+                match (is_reversed, run_start) {
+                    (true, None) => run_start = Some(x),
+                    (false, Some(start)) => {
+                        reversed.push_str(&format!("{y}: {start}..{x}\n"));
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(start) = run_start {
+                reversed.push_str(&format!("{y}: {start}..{}\n", buffer.area.width));
+            }
+            text.push('\n');
+        }
+        format!("{text}--- reversed ---\n{reversed}")
+    }
+
+    // compare against snapshots/<name>.snap, UPDATE_SNAPSHOTS=1 rewrites them
+    fn assert_snapshot(name: &str, app: &App) {
+        let rendered = render(app);
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots")
+            .join(format!("{name}.snap"));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &rendered).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "missing snapshot {}, run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display()
+            )
+        });
+        assert_eq!(rendered, expected, "Snapshot mismatch for: {}", name);
+    }
+
+    fn new_app() -> App {
+        App::new(&Resources::default(), Preferences::default(), Some(1)).unwrap()
+    }
+
+    // key of a phrase of the first section of lesson 1
+    fn phrase_key(app: &App, phrase_idx: usize) -> String {
+        app.progress_key(&app.book[1].sections[0].phrases[phrase_idx])
+    }
+
+    #[test]
+    fn test_welcome() {
+        let app = new_app();
+        assert_snapshot("welcome", &app);
+    }
+
+    #[test]
+    fn test_book_select() {
+        let mut app = new_app();
+        add_deck(
+            &mut app.library,
+            r#"[{"id": "person", "en": "person", "jp": "ひと"}]"#,
+        );
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Down)]);
+        assert_snapshot("book_select", &app);
+
+        press(&mut app, &[key(KeyCode::Enter)]);
+        assert!(render(&app).contains(" Lessons - JLPT N5 "));
+    }
+
+    #[test]
+    fn test_lesson_select() {
+        let mut app = new_app();
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Down)]);
+        assert_snapshot("lesson_select", &app);
+
+        // select a whole lesson and a section of another lesson
+        press(
+            &mut app,
+            &[
+                key(KeyCode::Char(' ')),
+                key(KeyCode::Down),
+                key(KeyCode::Down),
+                key(KeyCode::Right),
+                key(KeyCode::Down),
+                key(KeyCode::Char(' ')),
+                key(KeyCode::Char('d')),
+            ],
+        );
+        assert_snapshot("lesson_select_sections", &app);
+    }
+
+    #[test]
+    fn test_review() {
+        let mut app = new_app();
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Enter)]);
+        assert_snapshot("review_question", &app);
+
+        // highlight two kana and pick the second kanji candidate
+        type_text(&mut app, "nihon");
+        press(&mut app, &[key(KeyCode::Down), key(KeyCode::Tab)]);
+        press(
+            &mut app,
+            &[
+                key(KeyCode::Right),
+                shift(KeyCode::Right),
+                key(KeyCode::Tab),
+            ],
+        );
+        press(&mut app, &[key(KeyCode::Left), key(KeyCode::Down)]);
+        assert_snapshot("review_input", &app);
+
+        press(&mut app, &[key(KeyCode::Enter)]);
+        assert_snapshot("review_answer", &app);
+
+        // marks show under the question
+        press(&mut app, &[(KeyCode::Char('d'), KeyModifiers::CONTROL)]);
+        assert!(render(&app).contains("└ hard ─"));
+    }
+
+    #[test]
+    fn test_furigana() {
+        let mut app = new_app();
+        app.set_direction_mode(DirectionMode::ToEN);
+        app.context.lesson_idx = 3;
+        app.context.section_idx = Some(4);
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Enter)]);
+        while app.question().2.jp != "かえる" {
+            app.next_question();
+        }
+        assert_snapshot("review_furigana", &app);
+
+        // only the kanji once the readings are hidden
+        press(&mut app, &[(KeyCode::Char('r'), KeyModifiers::CONTROL)]);
+        assert!(app.preferences.hide_furigana);
+        let text = render(&app);
+        assert!(text.contains("│'帰る'"));
+        assert!(!text.contains("かえる"));
+    }
+
+    #[test]
+    fn test_reading_drill() {
+        let mut app = new_app();
+        app.set_drill(Drill::KanjiReading);
+        app.context.lesson_idx = 1;
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Enter)]);
+        type_text(&mut app, "hi");
+        press(&mut app, &[key(KeyCode::Enter)]);
+        assert_snapshot("review_kanji_reading", &app);
+    }
+
+    #[test]
+    fn test_kana_drill() {
+        let mut app = new_app();
+        app.set_direction_mode(DirectionMode::ToJP);
+        press(
+            &mut app,
+            &[
+                key(KeyCode::Enter),
+                key(KeyCode::Char('k')),
+                key(KeyCode::Char('k')),
+                key(KeyCode::Char('k')),
+            ],
+        );
+        assert_snapshot("lesson_select_kana", &app);
+        press(&mut app, &[key(KeyCode::Enter)]);
+        type_text(&mut app, "ka");
+        press(&mut app, &[key(KeyCode::Enter)]);
+        assert_snapshot("review_kana", &app);
+    }
+
+    #[test]
+    fn test_writing_drill() {
+        let mut app = new_app();
+        app.set_drill(Drill::Writing);
+        app.context.lesson_idx = 1;
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Enter)]);
+        type_text(&mut app, "nihon");
+        press(&mut app, &[key(KeyCode::Tab)]);
+        assert_snapshot("review_writing", &app);
+        press(&mut app, &[key(KeyCode::Enter)]);
+        assert_snapshot("review_writing_answer", &app);
+    }
+
+    #[test]
+    fn test_help() {
+        let mut app = new_app();
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Char('?'))]);
+        assert_snapshot("lesson_select_help", &app);
+
+        // keys go to the overlay until it is closed
+        press(&mut app, &[key(KeyCode::Down), key(KeyCode::Esc)]);
+        assert!(!app.show_help);
+        assert_eq!(app.context.lesson_idx, 0);
+
+        // ? is typed on the review screen, F1 opens help there
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Char('?'))]);
+        assert!(!app.show_help);
+        press(&mut app, &[key(KeyCode::F(1))]);
+        assert_snapshot("review_help", &app);
+    }
+
+    #[test]
+    fn test_browse() {
+        let mut app = new_app();
+        app.progress.record(phrase_key(&app, 0), true);
+        app.progress.record(phrase_key(&app, 1), false);
+        app.progress.toggle_mark(phrase_key(&app, 1), Mark::Hard);
+        app.progress.toggle_mark(phrase_key(&app, 2), Mark::Known);
+        press(
+            &mut app,
+            &[
+                key(KeyCode::Enter),
+                key(KeyCode::Down),
+                key(KeyCode::Char('b')),
+                key(KeyCode::Down),
+            ],
+        );
+        assert_snapshot("browse", &app);
+
+        type_text(&mut app, "gakusei");
+        assert_snapshot("browse_search", &app);
+
+        // notes and examples of the highlighted phrase
+        app.context.browse_query.clear();
+        app.context.lesson_idx = 3;
+        type_text(&mut app, "kaeru");
+        assert_snapshot("browse_details", &app);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut app = new_app();
+        press(&mut app, &[key(KeyCode::Enter), key(KeyCode::Char('/'))]);
+        type_text(&mut app, "time");
+        press(&mut app, &[key(KeyCode::Down)]);
+        assert_snapshot("search", &app);
+    }
+}